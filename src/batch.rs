@@ -0,0 +1,75 @@
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::boundary::Boundary;
+use crate::render_context::RenderContext;
+use crate::rule::Rule;
+
+/// Summary of a [`run`], for comparing runs in parameter sweeps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchStats {
+    pub grid_size: (u32, u32),
+    pub rule: Rule,
+    pub boundary: Boundary,
+    /// Seed of the random start, `None` when the board came from a pattern.
+    pub seed: Option<u64>,
+    /// Generations computed by this run.
+    pub generations: u64,
+    /// Generation count of the final board, which also includes the pattern's own generation.
+    pub generation: u64,
+    /// Live cells on the final board.
+    pub population: u64,
+    /// Time spent computing, not counting setup or reading the board back.
+    pub wall_time: Duration,
+}
+
+impl BatchStats {
+    pub fn generations_per_second(&self) -> f64 {
+        self.generations as f64 / self.wall_time.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Formats the stats as a single line JSON object.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+        write!(
+            json,
+            "\"width\":{},\"height\":{},\"rule\":\"{}\",\"boundary\":\"{}\",",
+            self.grid_size.0, self.grid_size.1, self.rule, self.boundary
+        )
+        .unwrap();
+        match self.seed {
+            Some(seed) => write!(json, "\"seed\":{seed},").unwrap(),
+            None => json.push_str("\"seed\":null,"),
+        }
+        write!(
+            json,
+            "\"generations\":{},\"generation\":{},\"population\":{},\"wall_time_secs\":{},\"generations_per_sec\":{}}}",
+            self.generations,
+            self.generation,
+            self.population,
+            self.wall_time.as_secs_f64(),
+            self.generations_per_second()
+        )
+        .unwrap();
+        json
+    }
+}
+
+/// Computes `generations` generations on `ctx` without drawing, as fast as the GPU allows, and
+/// reports on the final board. `seed` is only passed through to the stats.
+pub fn run(ctx: &mut RenderContext, generations: u64, seed: Option<u64>) -> BatchStats {
+    let start = Instant::now();
+    ctx.simulate(generations);
+    let wall_time = start.elapsed();
+
+    BatchStats {
+        grid_size: ctx.grid_size(),
+        rule: ctx.rule(),
+        boundary: ctx.boundary(),
+        seed,
+        generations,
+        generation: ctx.generation(),
+        population: ctx.population(),
+        wall_time,
+    }
+}
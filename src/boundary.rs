@@ -0,0 +1,113 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// What lies beyond the edges of the board when counting neighbours.
+///
+/// The discriminants are the values of the `boundary` uniform in `compute.wgsl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Boundary {
+    /// Opposite edges are joined, so patterns leaving one side come back on the other.
+    #[default]
+    Torus = 0,
+    /// Everything outside the board is permanently dead.
+    Dead = 1,
+    /// The board is reflected at its edges, so the row or column just outside mirrors the one
+    /// just inside.
+    Mirror = 2,
+    /// Like a torus, but crossing the top or bottom edge flips the board left to right.
+    KleinBottle = 3,
+    /// Crossing the top or bottom edge flips the board left to right, and crossing the left
+    /// or right edge flips it top to bottom (the real projective plane).
+    CrossSurface = 4,
+}
+
+impl Boundary {
+    pub const ALL: [Boundary; 5] = [
+        Boundary::Torus,
+        Boundary::Dead,
+        Boundary::Mirror,
+        Boundary::KleinBottle,
+        Boundary::CrossSurface,
+    ];
+
+    /// Maps a possibly out of bounds neighbour coordinate onto the `width` x `height` board,
+    /// or `None` if it's outside a dead border. Mirrors `cellActive` in `compute.wgsl`.
+    pub fn resolve(&self, x: i64, y: i64, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (width, height) = (width as i64, height as i64);
+        let outside_x = x < 0 || x >= width;
+        let outside_y = y < 0 || y >= height;
+        let (mut x, mut y) = (x, y);
+        match self {
+            Boundary::Torus => {}
+            Boundary::Dead => {
+                if outside_x || outside_y {
+                    return None;
+                }
+            }
+            Boundary::Mirror => {
+                x = x.clamp(0, width - 1);
+                y = y.clamp(0, height - 1);
+            }
+            Boundary::KleinBottle => {
+                if outside_y {
+                    x = width - 1 - x;
+                }
+            }
+            Boundary::CrossSurface => {
+                if outside_x {
+                    y = height - 1 - y;
+                }
+                if outside_y {
+                    x = width - 1 - x;
+                }
+            }
+        }
+        Some((x.rem_euclid(width) as u32, y.rem_euclid(height) as u32))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Boundary::Torus => "torus",
+            Boundary::Dead => "dead",
+            Boundary::Mirror => "mirror",
+            Boundary::KleinBottle => "klein",
+            Boundary::CrossSurface => "cross",
+        }
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBoundaryError(pub String);
+
+impl fmt::Display for ParseBoundaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown boundary {:?}, expected one of torus, dead, mirror, klein, cross",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseBoundaryError {}
+
+impl FromStr for Boundary {
+    type Err = ParseBoundaryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "torus" | "wrap" => Ok(Boundary::Torus),
+            "dead" | "plane" => Ok(Boundary::Dead),
+            "mirror" | "reflect" => Ok(Boundary::Mirror),
+            "klein" | "klein-bottle" => Ok(Boundary::KleinBottle),
+            "cross" | "cross-surface" => Ok(Boundary::CrossSurface),
+            _ => Err(ParseBoundaryError(s.to_string())),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use winit::dpi::PhysicalSize;
+
+/// Closest and furthest the camera zooms, in pixels per cell.
+pub const MIN_ZOOM: f64 = 0.01;
+pub const MAX_ZOOM: f64 = 512.;
+
+/// View onto the board, in board coordinates: cell `(x, y)` covers `x..x + 1` and `y..y + 1`,
+/// with `y` growing upwards like the rows of the board.
+///
+/// Cells are always square on screen, whatever the shape of the viewport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// Board point shown at the centre of the viewport.
+    pub center: (f64, f64),
+    /// Pixels per cell.
+    pub zoom: f64,
+}
+
+impl Camera {
+    /// Centres the board and zooms so all of it fits in `viewport`.
+    pub fn fit((width, height): (u32, u32), viewport: PhysicalSize<u32>) -> Camera {
+        let zoom = (viewport.width as f64 / width as f64)
+            .min(viewport.height as f64 / height as f64)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        Camera {
+            center: (width as f64 / 2., height as f64 / 2.),
+            zoom,
+        }
+    }
+
+    /// Contents of the `camera` uniform in `shader.wgsl`: the centre, and the clip space units
+    /// per cell along each axis.
+    pub fn to_uniform(&self, viewport: PhysicalSize<u32>) -> [f32; 4] {
+        [
+            self.center.0 as f32,
+            self.center.1 as f32,
+            (2. * self.zoom / viewport.width.max(1) as f64) as f32,
+            (2. * self.zoom / viewport.height.max(1) as f64) as f32,
+        ]
+    }
+
+    /// Maps a position in pixels from the top left of the viewport to board coordinates.
+    pub fn screen_to_board(&self, x: f64, y: f64, viewport: PhysicalSize<u32>) -> (f64, f64) {
+        (
+            self.center.0 + (x - viewport.width as f64 / 2.) / self.zoom,
+            self.center.1 - (y - viewport.height as f64 / 2.) / self.zoom,
+        )
+    }
+
+    /// Moves the view along with a drag of `dx`, `dy` pixels.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.center.0 -= dx / self.zoom;
+        self.center.1 += dy / self.zoom;
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the board point under pixel `(x, y)` in place.
+    pub fn zoom_at(&mut self, factor: f64, x: f64, y: f64, viewport: PhysicalSize<u32>) {
+        let before = self.screen_to_board(x, y, viewport);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let after = self.screen_to_board(x, y, viewport);
+        self.center.0 += before.0 - after.0;
+        self.center.1 += before.1 - after.1;
+    }
+
+    /// The columns and rows of a `width` x `height` board that are at least partly in view,
+    /// as inclusive `(x_min, x_max)` and `(y_min, y_max)` ranges, or `None` if none are.
+    pub fn visible_cells(
+        &self,
+        (width, height): (u32, u32),
+        viewport: PhysicalSize<u32>,
+    ) -> Option<((u32, u32), (u32, u32))> {
+        let (left, top) = self.screen_to_board(0., 0., viewport);
+        let (right, bottom) =
+            self.screen_to_board(viewport.width as f64, viewport.height as f64, viewport);
+        let range = |from: f64, to: f64, cells: u32| {
+            let first = from.floor().max(0.);
+            let last = (to.ceil() - 1.).min(cells as f64 - 1.);
+            (first <= last).then_some((first as u32, last as u32))
+        };
+        Some((range(left, right, width)?, range(bottom, top, height)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWPORT: PhysicalSize<u32> = PhysicalSize::new(400, 200);
+
+    fn camera(center: (f64, f64), zoom: f64) -> Camera {
+        Camera { center, zoom }
+    }
+
+    #[test]
+    fn screen_corners_map_to_the_board() {
+        let camera = camera((10., 5.), 20.);
+        assert_eq!(camera.screen_to_board(200., 100., VIEWPORT), (10., 5.));
+        // y grows downwards on screen but upwards on the board
+        assert_eq!(camera.screen_to_board(0., 0., VIEWPORT), (0., 10.));
+        assert_eq!(camera.screen_to_board(400., 200., VIEWPORT), (20., 0.));
+    }
+
+    #[test]
+    fn zooming_keeps_the_point_under_the_cursor() {
+        let mut camera = camera((10., 5.), 20.);
+        let (x, y) = (50., 170.);
+        let before = camera.screen_to_board(x, y, VIEWPORT);
+        for factor in [2., 0.3, 1e9, 1e-9] {
+            camera.zoom_at(factor, x, y, VIEWPORT);
+            let after = camera.screen_to_board(x, y, VIEWPORT);
+            assert!(
+                (after.0 - before.0).abs() < 1e-9 && (after.1 - before.1).abs() < 1e-9,
+                "{after:?} instead of {before:?} after zooming by {factor}"
+            );
+        }
+        assert_eq!(camera.zoom, MIN_ZOOM);
+    }
+
+    #[test]
+    fn fitted_boards_are_fully_visible() {
+        let camera = Camera::fit((32, 16), VIEWPORT);
+        assert_eq!(camera.zoom, 12.5);
+        assert_eq!(
+            camera.visible_cells((32, 16), VIEWPORT),
+            Some(((0, 31), (0, 15)))
+        );
+    }
+
+    #[test]
+    fn partly_visible_boards_cull_to_the_cells_in_view() {
+        // 40 x 20 cells in view, from (-20, -10) to (20, 10)
+        let centered_on_origin = camera((0., 0.), 10.);
+        assert_eq!(
+            centered_on_origin.visible_cells((32, 32), VIEWPORT),
+            Some(((0, 19), (0, 9)))
+        );
+        // half a cell further, so one more column and row is partly in view
+        let offset = camera((0.5, 0.5), 10.);
+        assert_eq!(
+            offset.visible_cells((32, 32), VIEWPORT),
+            Some(((0, 20), (0, 10)))
+        );
+        // from the middle to past the far corner
+        let far_corner = camera((32., 32.), 10.);
+        assert_eq!(
+            far_corner.visible_cells((32, 32), VIEWPORT),
+            Some(((12, 31), (22, 31)))
+        );
+    }
+
+    #[test]
+    fn off_screen_boards_have_no_visible_cells() {
+        for center in [
+            (-20., 16.),
+            (52., 16.),
+            (16., -10.),
+            (16., 42.),
+            (-100., -100.),
+        ] {
+            assert_eq!(
+                camera(center, 10.).visible_cells((32, 32), VIEWPORT),
+                None,
+                "{center:?}"
+            );
+        }
+    }
+}
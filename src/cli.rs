@@ -0,0 +1,349 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::boundary::Boundary;
+use crate::clock::{DEFAULT_TICK_RATE, MAX_TICK_RATE, MIN_TICK_RATE};
+use crate::pattern::{Pattern, PatternError, Placement};
+use crate::primitives::DEFAULT_GRID_SIZE;
+use crate::render_context::{Antialiasing, RenderContext};
+use crate::rule::Rule;
+use crate::seed::{SeedPattern, Seeding, DEFAULT_DENSITY};
+use crate::step_rate::StepRate;
+
+/// Size in pixels of the window, or of the render target when headless.
+pub const DEFAULT_WINDOW_SIZE: (u32, u32) = (800, 800);
+
+pub const USAGE: &str = "\
+Usage: wgpu_learn_02 [OPTIONS]
+
+Board:
+  --grid WxH             board size in cells [default: 32x32]
+  --rule RULE            life-like rule, e.g. B3/S23, B36/S23 or B2/S/C3 [default: B3/S23]
+  --boundary MODE        torus, dead, mirror, klein or cross [default: torus]
+  --pattern FILE         start from a .rle, .cells or .lif pattern instead of random cells
+
+Random start:
+  --seed N               seed for the random start [default: random]
+  --density F            fraction of live cells, from 0 to 1 [default: 0.4]
+  --seeder KIND          soup, symmetric, blob[:RADIUS] or stripes [default: soup]
+
+Running:
+  --headless             run --generations generations without a window or FPS cap, then
+                         print stats as JSON
+  --generations N        stop after N generations, required with --headless
+  --rate HZ              generations per second in the window, independent of the frame
+                         rate [default: 20]
+  --speed RATE           instead of --rate, tie generations to frames: e.g. 4 per frame,
+                         or 1/3 for one every 3 frames, up to 1024 per frame
+  --fps N                cap the window's frame rate [default: the display's refresh rate]
+  --size WxH             window or render target size in pixels [default: 800x800]
+  --aa MODE              antialiasing: none, msaa (4x) or analytic [default: msaa]
+
+Output, written when the run stops:
+  --output FILE          save the board as RLE
+  --screenshot FILE      save the last frame as PNG
+  --stats FILE           write the headless stats to FILE instead of stdout
+
+  -h, --help             print this help
+
+Keys in the window:
+  space                  pause or resume
+  N, right arrow         advance one generation while paused
+  R                      reseed with a new random seed
+  C                      clear the board
+  +, -                   double or halve --rate, or --speed
+  F                      zoom to fit the board
+
+Mouse in the window:
+  left / right drag      draw / erase cells
+  middle or ctrl+left    pan
+  wheel                  zoom
+";
+
+/// Everything that can be set from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub grid_size: (u32, u32),
+    /// Overrides the rule from the pattern file, if there is one.
+    pub rule: Option<Rule>,
+    pub boundary: Boundary,
+    pub pattern: Option<PathBuf>,
+    pub seeding: Seeding,
+    pub headless: bool,
+    pub generations: Option<u64>,
+    /// Generations per second in the window.
+    pub tick_rate: f64,
+    /// Generations per frame in the window, replacing `tick_rate` when set.
+    pub step_rate: Option<StepRate>,
+    /// Most frames per second the window draws, if capped.
+    pub fps_cap: Option<f64>,
+    pub window_size: (u32, u32),
+    pub antialiasing: Antialiasing,
+    pub output: Option<PathBuf>,
+    pub screenshot: Option<PathBuf>,
+    /// Where headless runs write their JSON stats, stdout if `None`.
+    pub stats: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            grid_size: DEFAULT_GRID_SIZE,
+            rule: None,
+            boundary: Boundary::default(),
+            pattern: None,
+            seeding: Seeding::random(),
+            headless: false,
+            generations: None,
+            tick_rate: DEFAULT_TICK_RATE,
+            step_rate: None,
+            fps_cap: None,
+            window_size: DEFAULT_WINDOW_SIZE,
+            antialiasing: Antialiasing::default(),
+            output: None,
+            screenshot: None,
+            stats: None,
+        }
+    }
+}
+
+impl Options {
+    /// Parses the arguments after the program name. Values can be given as `--flag value` or
+    /// `--flag=value`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, CliError> {
+        let mut options = Options::default();
+        let mut seed = None;
+        let mut density = DEFAULT_DENSITY;
+        let mut seeder = SeedPattern::default();
+        let mut rate = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| CliError::MissingValue(flag.clone()))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Err(CliError::Help),
+                "--headless" => options.headless = true,
+                "--grid" => options.grid_size = parse_grid_size(&flag, &value()?)?,
+                "--size" => options.window_size = parse_size(&flag, &value()?)?,
+                "--aa" => options.antialiasing = parse_value(&flag, &value()?)?,
+                "--rule" => options.rule = Some(parse_value(&flag, &value()?)?),
+                "--boundary" => options.boundary = parse_value(&flag, &value()?)?,
+                "--pattern" => options.pattern = Some(PathBuf::from(value()?)),
+                "--seed" => seed = Some(parse_value(&flag, &value()?)?),
+                "--density" => density = parse_value(&flag, &value()?)?,
+                "--seeder" => seeder = parse_value(&flag, &value()?)?,
+                "--generations" => options.generations = Some(parse_value(&flag, &value()?)?),
+                "--rate" => rate = Some(parse_value(&flag, &value()?)?),
+                "--speed" => options.step_rate = Some(parse_value(&flag, &value()?)?),
+                "--fps" => options.fps_cap = Some(parse_value(&flag, &value()?)?),
+                "--output" => options.output = Some(PathBuf::from(value()?)),
+                "--screenshot" => options.screenshot = Some(PathBuf::from(value()?)),
+                "--stats" => options.stats = Some(PathBuf::from(value()?)),
+                _ => return Err(CliError::UnknownFlag(flag)),
+            }
+        }
+
+        if !(0. ..=1.).contains(&density) {
+            return Err(CliError::InvalidValue {
+                flag: "--density".to_string(),
+                value: density.to_string(),
+                reason: "must be between 0 and 1".to_string(),
+            });
+        }
+        if let Some(fps) = options
+            .fps_cap
+            .filter(|fps| !(fps.is_finite() && *fps > 0.))
+        {
+            return Err(CliError::InvalidValue {
+                flag: "--fps".to_string(),
+                value: fps.to_string(),
+                reason: "must be positive".to_string(),
+            });
+        }
+        if rate.is_some() && options.step_rate.is_some() {
+            return Err(CliError::Conflicts {
+                flag: "--speed".to_string(),
+                other: "--rate".to_string(),
+            });
+        }
+        options.tick_rate = rate.unwrap_or(DEFAULT_TICK_RATE);
+        if !(MIN_TICK_RATE..=MAX_TICK_RATE).contains(&options.tick_rate) {
+            return Err(CliError::InvalidValue {
+                flag: "--rate".to_string(),
+                value: options.tick_rate.to_string(),
+                reason: format!("must be between {MIN_TICK_RATE} and {MAX_TICK_RATE}"),
+            });
+        }
+        // without it a headless run would quietly report the starting board
+        if options.headless && options.generations.is_none() {
+            return Err(CliError::Requires {
+                flag: "--headless".to_string(),
+                required: "--generations".to_string(),
+            });
+        }
+        options.seeding = seed
+            .map_or_else(Seeding::random, Seeding::new)
+            .with_density(density)
+            .with_pattern(seeder);
+        Ok(options)
+    }
+
+    /// Reads the `--pattern` file, if one was given.
+    pub fn load_pattern(&self) -> Result<Option<Pattern>, PatternError> {
+        self.pattern.as_ref().map(Pattern::load).transpose()
+    }
+
+    /// Sets up the board of a freshly created context: the pattern if there is one, otherwise
+    /// random cells, then the rule and boundary.
+    pub fn configure(&self, ctx: &mut RenderContext, pattern: Option<&Pattern>) {
+        match pattern {
+            Some(pattern) => ctx.load_pattern(pattern, Placement::Centered),
+            None => ctx.reseed(self.seeding),
+        }
+        if let Some(rule) = self.rule {
+            ctx.set_rule(rule);
+        }
+        ctx.set_boundary(self.boundary);
+        if let Some(step_rate) = self.step_rate {
+            ctx.set_step_rate(step_rate);
+        }
+    }
+
+    /// Saves the board and last frame to the `--output` and `--screenshot` paths.
+    pub fn write_outputs(&self, ctx: &RenderContext) -> io::Result<()> {
+        if let Some(path) = &self.output {
+            ctx.to_pattern().save_rle(path)?;
+        }
+        if let Some(path) = &self.screenshot {
+            ctx.read_frame().map_err(io::Error::other)?.save_png(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_value<T>(flag: &str, value: &str) -> Result<T, CliError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value.parse().map_err(|e: T::Err| CliError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Parses `WxH`, e.g. `1920x1080`.
+fn parse_size(flag: &str, value: &str) -> Result<(u32, u32), CliError> {
+    let invalid = || CliError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+        reason: "expected WIDTHxHEIGHT with both at least 1".to_string(),
+    };
+    let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+    match (width.trim().parse(), height.trim().parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a board size like [`parse_size`], also making sure every cell has a `u32` index.
+/// Whether the GPU can hold that many is only known once there's a device.
+fn parse_grid_size(flag: &str, value: &str) -> Result<(u32, u32), CliError> {
+    let (width, height) = parse_size(flag, value)?;
+    if width.checked_mul(height).is_none() {
+        return Err(CliError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+            reason: format!("more than {} cells", u32::MAX),
+        });
+    }
+    Ok((width, height))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    /// `--help` was passed, the caller should print [`USAGE`].
+    Help,
+    UnknownFlag(String),
+    MissingValue(String),
+    /// `flag` and `other` were both given, but only one of them can be used.
+    Conflicts {
+        flag: String,
+        other: String,
+    },
+    /// `flag` only works together with `required`, which wasn't given.
+    Requires {
+        flag: String,
+        required: String,
+    },
+    InvalidValue {
+        flag: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help => f.write_str(USAGE),
+            CliError::UnknownFlag(flag) => write!(f, "unknown option {flag}"),
+            CliError::MissingValue(flag) => write!(f, "{flag} needs a value"),
+            CliError::Conflicts { flag, other } => write!(f, "{flag} can't be used with {other}"),
+            CliError::Requires { flag, required } => write!(f, "{flag} needs {required}"),
+            CliError::InvalidValue {
+                flag,
+                value,
+                reason,
+            } => write!(f, "invalid value {value:?} for {flag}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, CliError> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn headless_needs_generations() {
+        assert!(matches!(
+            parse(&["--headless"]),
+            Err(CliError::Requires { .. })
+        ));
+        let options = parse(&["--headless", "--generations=10"]).unwrap();
+        assert_eq!(options.generations, Some(10));
+    }
+
+    #[test]
+    fn speed_and_fps_are_kept_alongside_rate() {
+        let options = parse(&["--speed", "1/3", "--fps", "30"]).unwrap();
+        assert_eq!(options.step_rate, Some("1/3".parse().unwrap()));
+        assert_eq!(options.fps_cap, Some(30.));
+        assert!(matches!(
+            parse(&["--speed", "2", "--rate", "10"]),
+            Err(CliError::Conflicts { .. })
+        ));
+        assert!(parse(&["--fps", "0"]).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+/// Generations per second when no rate is given.
+pub const DEFAULT_TICK_RATE: f64 = 20.;
+
+/// Slowest and fastest rates the clock runs at, anything outside is clamped.
+pub const MIN_TICK_RATE: f64 = 0.01;
+pub const MAX_TICK_RATE: f64 = 10_000.;
+
+/// Longest stretch of time a single [`SimulationClock::tick`] catches up on. After a stall
+/// (a dragged window, a slow frame) the simulation skips ahead instead of trying to compute
+/// everything it missed at once and falling further behind.
+const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+/// Fixed timestep clock that runs the simulation at a steady rate independent of how often
+/// frames are drawn. Each frame asks it how many generations are due.
+#[derive(Debug, Clone)]
+pub struct SimulationClock {
+    rate: f64,
+    accumulator: Duration,
+    last_tick: Instant,
+}
+
+impl SimulationClock {
+    /// A clock ticking `rate` times a second.
+    pub fn new(rate: f64) -> SimulationClock {
+        SimulationClock {
+            rate: rate.clamp(MIN_TICK_RATE, MAX_TICK_RATE),
+            accumulator: Duration::ZERO,
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// Generations per second.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Changes the number of generations per second, starting with the next tick. Time already
+    /// accumulated carries over.
+    pub fn set_rate(&mut self, rate: f64) {
+        self.rate = rate.clamp(MIN_TICK_RATE, MAX_TICK_RATE);
+    }
+
+    /// Time between two generations.
+    pub fn timestep(&self) -> Duration {
+        Duration::from_secs_f64(1. / self.rate)
+    }
+
+    /// Returns how many generations are due since the last tick, keeping the left over time
+    /// for the next one.
+    pub fn tick(&mut self) -> u64 {
+        let now = Instant::now();
+        let timestep = self.timestep();
+        self.accumulator =
+            (self.accumulator + (now - self.last_tick)).min(MAX_CATCH_UP.max(timestep));
+        self.last_tick = now;
+
+        let due = (self.accumulator.as_secs_f64() / timestep.as_secs_f64()) as u64;
+        self.accumulator = self
+            .accumulator
+            .saturating_sub(timestep.mul_f64(due as f64));
+        due
+    }
+
+    /// Drops any accumulated time, e.g. when resuming so the paused time isn't caught up on.
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+        self.last_tick = Instant::now();
+    }
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        SimulationClock::new(DEFAULT_TICK_RATE)
+    }
+}
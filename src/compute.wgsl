@@ -0,0 +1,88 @@
+@group(0) @binding(0) var<uniform> grid: vec2f;
+@group(0) @binding(1) var<storage> cell_state_in: array<u32>;
+@group(0) @binding(2) var<storage, read_write> cell_state_out: array<u32>;
+
+// bit n of birth/survive is set when a dead/live cell with n live neighbors is alive next step.
+// states > 2 is a Generations rule, where dying cells count up through states 2..states-1
+struct Rule {
+    birth: u32,
+    survive: u32,
+    states: u32,
+}
+@group(0) @binding(3) var<uniform> rule: Rule;
+
+// how neighbors beyond the edges are found, see `Boundary` on the CPU side
+const BOUNDARY_DEAD: u32 = 1u;
+const BOUNDARY_MIRROR: u32 = 2u;
+const BOUNDARY_KLEIN_BOTTLE: u32 = 3u;
+const BOUNDARY_CROSS_SURFACE: u32 = 4u;
+@group(0) @binding(4) var<uniform> boundary: u32;
+
+fn cellIndex(cell: vec2u) -> u32 {
+    return (cell.y % u32(grid.y)) * u32(grid.x) + (cell.x % u32(grid.x));
+}
+// only live cells count as neighbors, not refractory ones
+fn cellActive(x: i32, y: i32) -> u32 {
+    let size = vec2i(grid);
+    let outside_x = x < 0 || x >= size.x;
+    let outside_y = y < 0 || y >= size.y;
+    var p = vec2i(x, y);
+    switch boundary {
+        case BOUNDARY_DEAD: {
+            if (outside_x || outside_y) {
+                return 0u;
+            }
+        }
+        case BOUNDARY_MIRROR: {
+            p = clamp(p, vec2i(0), size - 1);
+        }
+        case BOUNDARY_KLEIN_BOTTLE: {
+            if (outside_y) {
+                p.x = size.x - 1 - p.x;
+            }
+        }
+        case BOUNDARY_CROSS_SURFACE: {
+            if (outside_x) {
+                p.y = size.y - 1 - p.y;
+            }
+            if (outside_y) {
+                p.x = size.x - 1 - p.x;
+            }
+        }
+        // torus
+        default: {}
+    }
+    // wrap into 0..size. Neighbors are at most one cell out, and % isn't used because it's
+    // undefined for negative numbers on some backends (GLSL)
+    p = select(p, p + size, p < vec2i(0));
+    p = select(p, p - size, p >= size);
+    return select(0u, 1u, cell_state_in[cellIndex(vec2u(p))] == 1u);
+}
+@compute @workgroup_size(8,8)
+fn compute_main(@builtin(global_invocation_id) cell: vec3u) {
+    // the last workgroups overhang the grid when it isn't a multiple of the workgroup size
+    if (cell.x >= u32(grid.x) || cell.y >= u32(grid.y)) {
+        return;
+    }
+// getting count of active neighbors
+    let x = i32(cell.x);
+    let y = i32(cell.y);
+    let active_neighbors = cellActive(x+1, y+1) +
+        cellActive(x+1, y) +
+        cellActive(x+1, y-1) +
+        cellActive(x, y-1) +
+        cellActive(x-1, y-1) +
+        cellActive(x-1, y) +
+        cellActive(x-1, y+1) +
+        cellActive(x, y+1);
+
+    let i = cellIndex(cell.xy);
+    let state = cell_state_in[i];
+    if (state == 0u) {
+        cell_state_out[i] = (rule.birth >> active_neighbors) & 1u;
+    } else if (state == 1u && ((rule.survive >> active_neighbors) & 1u) == 1u) {
+        cell_state_out[i] = 1u;
+    } else {
+        cell_state_out[i] = (state + 1u) % rule.states;
+    }
+}
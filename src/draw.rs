@@ -0,0 +1,162 @@
+//! Immediate mode drawing of filled shapes on top of the board. Shapes are queued on a
+//! [`DrawList`] every frame and drawn by the next [`RenderContext::render`], which then empties
+//! the list.
+//!
+//! Coordinates are board coordinates, the same as the camera's: cell `(x, y)` covers
+//! `x..x + 1` and `y..y + 1`, with `y` growing upwards.
+//!
+//! [`RenderContext::render`]: crate::render_context::RenderContext::render
+
+use std::f32::consts::TAU;
+
+use crate::primitives::{Color, Point2, ShapeVertex, Vertex};
+use crate::tessellate::{self, StrokeStyle};
+
+/// Number of edges a circle is approximated with.
+pub const CIRCLE_SEGMENTS: u32 = 48;
+
+/// Outline masks of a quad split into triangles `0, 1, 2` and `2, 3, 0`: the first two edges
+/// of each, but not the diagonal.
+const QUAD_OUTLINE: [u8; 2] = [0b011, 0b011];
+
+/// Triangles queued for the next frame, as indexed vertices.
+#[derive(Debug, Clone, Default)]
+pub struct DrawList {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    /// Which edges of each triangle are on a shape's outline, as in [`tessellate::Mesh`].
+    outline: Vec<u8>,
+}
+
+impl DrawList {
+    pub fn new() -> DrawList {
+        DrawList::default()
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Every triangle's corners on their own, with their distances to its outline edges, as
+    /// the shape pipeline draws them.
+    pub fn shape_vertices(&self) -> Vec<ShapeVertex> {
+        let mut shape_vertices = Vec::with_capacity(self.indices.len());
+        for (triangle, &outline) in self.indices.chunks_exact(3).zip(&self.outline) {
+            for (corner, &index) in triangle.iter().enumerate() {
+                // edge `k` runs between corners `k` and `k + 1`, so the corner opposite it
+                // is the one at barycentric distance 1
+                let edges = [0, 1, 2].map(|k| {
+                    let on_outline = outline & 1 << k != 0;
+                    if !on_outline || corner == (k + 2) % 3 {
+                        1.
+                    } else {
+                        0.
+                    }
+                });
+                shape_vertices.push(ShapeVertex {
+                    vertex: self.vertices[index as usize],
+                    edges,
+                });
+            }
+        }
+        shape_vertices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Drops everything queued, keeping the allocations for the next frame.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.outline.clear();
+    }
+
+    /// Appends `points` as vertices of one colour, `indices` into them, and an outline mask
+    /// per triangle.
+    fn push(
+        &mut self,
+        points: &[Point2],
+        indices: impl IntoIterator<Item = u32>,
+        outline: impl IntoIterator<Item = u8>,
+        color: Color,
+    ) {
+        let base = self.vertices.len() as u32;
+        self.vertices
+            .extend(points.iter().map(|&point| Vertex::new(point, color)));
+        self.indices.extend(indices.into_iter().map(|i| base + i));
+        self.outline.extend(outline);
+    }
+
+    pub fn draw_triangle(&mut self, a: Point2, b: Point2, c: Point2, color: Color) {
+        self.push(&[a, b, c], [0, 1, 2], [0b111], color);
+    }
+
+    /// An axis aligned rectangle between two opposite corners.
+    pub fn draw_rect(&mut self, min: Point2, max: Point2, color: Color) {
+        let corners = [
+            min,
+            Point2::new(max.x, min.y),
+            max,
+            Point2::new(min.x, max.y),
+        ];
+        self.push(&corners, [0, 1, 2, 2, 3, 0], QUAD_OUTLINE, color);
+    }
+
+    /// A straight line `width` wide, with square ends flush with `from` and `to`.
+    pub fn draw_line(&mut self, from: Point2, to: Point2, width: f32, color: Color) {
+        let length = from.distance(to);
+        if length == 0. {
+            return;
+        }
+        // half the width along the line's normal
+        let nx = -(to.y - from.y) / length * width / 2.;
+        let ny = (to.x - from.x) / length * width / 2.;
+        let corners = [
+            Point2::new(from.x + nx, from.y + ny),
+            Point2::new(from.x - nx, from.y - ny),
+            Point2::new(to.x - nx, to.y - ny),
+            Point2::new(to.x + nx, to.y + ny),
+        ];
+        self.push(&corners, [0, 1, 2, 2, 3, 0], QUAD_OUTLINE, color);
+    }
+
+    /// The outline of the polyline through `points`, see [`tessellate::stroke`].
+    pub fn draw_path(
+        &mut self,
+        points: &[Point2],
+        closed: bool,
+        style: &StrokeStyle,
+        color: Color,
+    ) {
+        let mesh = tessellate::stroke(points, closed, style);
+        self.push(&mesh.vertices, mesh.indices, mesh.outline, color);
+    }
+
+    /// A filled circle, approximated by a [`CIRCLE_SEGMENTS`] sided polygon.
+    pub fn draw_circle(&mut self, center: Point2, radius: f32, color: Color) {
+        let mut points = vec![center];
+        points.extend((0..CIRCLE_SEGMENTS).map(|i| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            Point2::new(
+                center.x + radius * angle.cos(),
+                center.y + radius * angle.sin(),
+            )
+        }));
+        let fan = (0..CIRCLE_SEGMENTS).flat_map(|i| [0, i + 1, (i + 1) % CIRCLE_SEGMENTS + 1]);
+        // only the rim of each slice, between its two points on the circle
+        let rim = [0b010; CIRCLE_SEGMENTS as usize];
+        self.push(&points, fan, rim, color);
+    }
+
+    /// A filled simple polygon (edges may not cross), convex or not, in either winding order.
+    pub fn draw_polygon(&mut self, points: &[Point2], color: Color) {
+        let mesh = tessellate::fill(points);
+        self.push(&mesh.vertices, mesh.indices, mesh.outline, color);
+    }
+}
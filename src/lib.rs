@@ -1,5 +1,17 @@
+pub mod batch;
+pub mod boundary;
+pub mod camera;
+pub mod cli;
+pub mod clock;
+pub mod draw;
+pub mod life;
+pub mod pattern;
 pub mod primitives;
 pub mod render_context;
+pub mod rule;
+pub mod seed;
+pub mod step_rate;
+pub mod tessellate;
 pub mod window;
 
 pub use window::App;
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use crate::boundary::Boundary;
+use crate::render_context::RenderContext;
+use crate::rule::Rule;
+
+/// Index of the cell at `(x, y)`, wrapping both coordinates around the board. Mirrors
+/// `cellIndex` in `compute.wgsl`.
+pub fn cell_index(x: u32, y: u32, width: u32, height: u32) -> usize {
+    ((y % height) * width + (x % width)) as usize
+}
+
+/// Advances `cells` (row-major, one `u32` per cell) by one generation of `rule` on a `width` x
+/// `height` board with the given `boundary`. This is the CPU reference for `compute_main`.
+pub fn step(cells: &[u32], width: u32, height: u32, rule: &Rule, boundary: Boundary) -> Vec<u32> {
+    // only live cells count as neighbours, not refractory ones
+    let active = |x: i64, y: i64| match boundary.resolve(x, y, width, height) {
+        Some((x, y)) => (cells[cell_index(x, y, width, height)] == 1) as u32,
+        None => 0,
+    };
+    let mut next = vec![0; cells.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let (cx, cy) = (x as i64, y as i64);
+            let active_neighbors = active(cx + 1, cy + 1)
+                + active(cx + 1, cy)
+                + active(cx + 1, cy - 1)
+                + active(cx, cy - 1)
+                + active(cx - 1, cy - 1)
+                + active(cx - 1, cy)
+                + active(cx - 1, cy + 1)
+                + active(cx, cy + 1);
+
+            let i = cell_index(x, y, width, height);
+            next[i] = rule.next_state(cells[i], active_neighbors);
+        }
+    }
+    next
+}
+
+/// First cell where the CPU reference and the GPU disagreed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Generation the boards differ at, counting from 1 for the first step.
+    pub generation: u32,
+    pub x: u32,
+    pub y: u32,
+    pub cpu: u32,
+    pub gpu: u32,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "generation {}: cell ({}, {}) is {} on the CPU but {} on the GPU",
+            self.generation, self.x, self.y, self.cpu, self.gpu
+        )
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// Runs `generations` steps from `initial` on both the CPU reference and `ctx`, comparing the
+/// boards after every step. Use with [`RenderContext::new_headless`] to validate the compute
+/// shader without a display.
+pub fn compare_with_gpu(
+    ctx: &mut RenderContext,
+    initial: &[u32],
+    generations: u32,
+) -> Result<(), Divergence> {
+    let (width, height) = ctx.grid_size();
+    let rule = ctx.rule();
+    let boundary = ctx.boundary();
+    ctx.write_cells(initial);
+    let mut cpu_cells = initial.to_vec();
+    for generation in 1..=generations {
+        cpu_cells = step(&cpu_cells, width, height, &rule, boundary);
+        ctx.simulate(1);
+        let gpu_cells = ctx.read_cells();
+        if let Some(i) = (0..cpu_cells.len()).find(|&i| cpu_cells[i] != gpu_cells[i]) {
+            return Err(Divergence {
+                generation,
+                x: i as u32 % width,
+                y: i as u32 / width,
+                cpu: cpu_cells[i],
+                gpu: gpu_cells[i],
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_context::Antialiasing;
+    use crate::seed::Seeding;
+
+    /// Pixels per cell in the frames of [`headless`] contexts.
+    const CELL_PIXELS: u32 = 10;
+
+    /// A headless context with a `width` x `height` board filling the frame, or `None` when
+    /// this machine has no adapter at all, in which case the test is skipped.
+    fn headless(width: u32, height: u32) -> Option<RenderContext<'static>> {
+        let (frame_width, frame_height) = (width * CELL_PIXELS, height * CELL_PIXELS);
+        match RenderContext::new_headless(
+            frame_width,
+            frame_height,
+            (width, height),
+            Antialiasing::None,
+        ) {
+            Ok(ctx) => Some(ctx),
+            Err(e) => {
+                eprintln!("skipping GPU test: {e}");
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn gpu_matches_cpu_for_every_boundary_and_rule() {
+        let Some(mut ctx) = headless(24, 20) else {
+            return;
+        };
+        let initial = Seeding::new(7).generate(24, 20);
+        for rule in [Rule::CONWAY, Rule::STAR_WARS] {
+            ctx.set_rule(rule);
+            for boundary in Boundary::ALL {
+                ctx.set_boundary(boundary);
+                let result = compare_with_gpu(&mut ctx, &initial, 16);
+                assert!(result.is_ok(), "{rule} on {boundary}: {result:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn odd_sized_boards_step_like_the_cpu() {
+        for (width, height) in [(7, 13), (13, 7)] {
+            let Some(mut ctx) = headless(width, height) else {
+                return;
+            };
+            let initial = Seeding::new(3).generate(width, height);
+            for boundary in [Boundary::Torus, Boundary::Dead] {
+                ctx.set_boundary(boundary);
+                let result = compare_with_gpu(&mut ctx, &initial, 12);
+                assert!(result.is_ok(), "{width}x{height} {boundary}: {result:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn odd_sized_boards_draw_every_cell_in_its_place() {
+        for (width, height) in [(7, 13), (13, 7)] {
+            let Some(mut ctx) = headless(width, height) else {
+                return;
+            };
+            let cells: Vec<u32> = (0..width * height)
+                .map(|i| ((i % width) * 3 + (i / width) * 5) % 4 == 0)
+                .map(u32::from)
+                .collect();
+            ctx.write_cells(&cells);
+            ctx.render_generations(0);
+            let frame = ctx.read_frame().unwrap();
+            for y in 0..height {
+                for x in 0..width {
+                    // rows count up from the bottom of the frame
+                    let pixel = frame.pixel(
+                        x * CELL_PIXELS + CELL_PIXELS / 2,
+                        (height - y) * CELL_PIXELS - CELL_PIXELS / 2,
+                    );
+                    let drawn = pixel[0] > 128;
+                    let alive = cells[cell_index(x, y, width, height)] == 1;
+                    assert_eq!(drawn, alive, "{width}x{height} cell ({x}, {y})");
+                }
+            }
+        }
+    }
+}
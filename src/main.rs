@@ -1,11 +1,80 @@
+use std::fs;
+use std::process::ExitCode;
+
+use wgpu_learn_02::batch;
+use wgpu_learn_02::cli::{CliError, Options};
+use wgpu_learn_02::render_context::RenderContext;
 use wgpu_learn_02::App;
 use winit::window::WindowAttributes;
 
-fn main() {
-    // pollster::block_on(run());
-    let mut app = App::init(Some(
-        WindowAttributes::default()
-            .with_title("WGPU LEARN")
-            .with_theme(Some(winit::window::Theme::Dark)),
-    ));
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(CliError::Help) => {
+            print!("{}", CliError::Help);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("ERROR: {e}\n\n{}", CliError::Help);
+            return ExitCode::FAILURE;
+        }
+    };
+    // load the pattern before opening anything so a bad file fails straight away
+    let pattern = match options.load_pattern() {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("ERROR: loading pattern failed: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if pattern.is_none() {
+        eprintln!("seed: {}", options.seeding.seed);
+    }
+
+    if !options.headless {
+        // pollster::block_on(run());
+        let _app = App::init(
+            Some(
+                WindowAttributes::default()
+                    .with_title("WGPU LEARN")
+                    .with_theme(Some(winit::window::Theme::Dark)),
+            ),
+            options,
+            pattern,
+        );
+        return ExitCode::SUCCESS;
+    }
+
+    let (width, height) = options.window_size;
+    let mut ctx =
+        match RenderContext::new_headless(width, height, options.grid_size, options.antialiasing) {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                eprintln!("ERROR: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+    options.configure(&mut ctx, pattern.as_ref());
+    let seed = pattern.is_none().then_some(options.seeding.seed);
+    let generations = options
+        .generations
+        .expect("ERROR: Options::parse requires --generations with --headless.");
+    let stats = batch::run(&mut ctx, generations, seed);
+
+    let written = options
+        .write_outputs(&ctx)
+        .and_then(|()| match &options.stats {
+            Some(path) => fs::write(path, stats.to_json() + "\n"),
+            None => {
+                println!("{}", stats.to_json());
+                Ok(())
+            }
+        });
+    if let Err(e) = written {
+        eprintln!("ERROR: writing output failed: {e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
 }
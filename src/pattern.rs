@@ -0,0 +1,440 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::rule::Rule;
+
+mod lif;
+mod plaintext;
+mod rle;
+
+/// A rectangular pattern of cells loaded from a pattern file.
+///
+/// Rows are stored top to bottom, the way they're written in files and shown on screen. The
+/// board in `RenderContext` keeps row 0 at the bottom instead, which [`Pattern::place`] takes
+/// care of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    /// One state per cell, row-major.
+    pub cells: Vec<u32>,
+    /// The rule given in the file, if any.
+    pub rule: Option<Rule>,
+    /// How many generations the pattern was run for before it was saved, if known.
+    pub generation: Option<u64>,
+}
+
+/// Most cells a pattern can have, 64 Mi (256 MiB of states). Pattern files that declare or
+/// span more are rejected instead of allocated.
+pub const MAX_PATTERN_CELLS: u64 = 1 << 26;
+
+/// Where a pattern goes on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Placement {
+    #[default]
+    Centered,
+    /// Top left cell of the pattern, counted from the top left corner of the board.
+    At { x: i64, y: i64 },
+}
+
+impl Pattern {
+    /// Creates an all dead pattern.
+    ///
+    /// Panics if it would have more than [`MAX_PATTERN_CELLS`] cells.
+    pub fn new(width: u32, height: u32) -> Pattern {
+        let cell_count = Pattern::cell_count(width as u64, height as u64)
+            .expect("ERROR: The pattern has more cells than MAX_PATTERN_CELLS.");
+        Pattern {
+            width,
+            height,
+            cells: vec![0; cell_count],
+            rule: None,
+            generation: None,
+        }
+    }
+
+    /// Number of cells in a `width` x `height` pattern, or `None` if that's more than
+    /// [`MAX_PATTERN_CELLS`].
+    pub fn cell_count(width: u64, height: u64) -> Option<usize> {
+        width
+            .checked_mul(height)
+            .filter(|&count| count <= MAX_PATTERN_CELLS)
+            .map(|count| count as usize)
+    }
+
+    /// Copies a `width` x `height` board (row-major, row 0 at the bottom) into a pattern.
+    pub fn from_board(board: &[u32], width: u32, height: u32) -> Pattern {
+        let mut pattern = Pattern::new(width, height);
+        for y in 0..height {
+            let row = (height - 1 - y) as usize * width as usize;
+            pattern.cells[(y * width) as usize..((y + 1) * width) as usize]
+                .copy_from_slice(&board[row..row + width as usize]);
+        }
+        pattern
+    }
+
+    /// The smallest part of the pattern that still contains every non-dead cell.
+    pub fn cropped(&self) -> Pattern {
+        let occupied = |x: u32, y: u32| self.get(x, y) != 0;
+        let rows: Vec<u32> = (0..self.height)
+            .filter(|&y| (0..self.width).any(|x| occupied(x, y)))
+            .collect();
+        let columns: Vec<u32> = (0..self.width)
+            .filter(|&x| (0..self.height).any(|y| occupied(x, y)))
+            .collect();
+        let (Some(&top), Some(&bottom), Some(&left), Some(&right)) =
+            (rows.first(), rows.last(), columns.first(), columns.last())
+        else {
+            return Pattern {
+                rule: self.rule,
+                generation: self.generation,
+                ..Pattern::new(0, 0)
+            };
+        };
+
+        let mut cropped = Pattern {
+            rule: self.rule,
+            generation: self.generation,
+            ..Pattern::new(right - left + 1, bottom - top + 1)
+        };
+        for y in top..=bottom {
+            for x in left..=right {
+                cropped.set(x - left, y - top, self.get(x, y));
+            }
+        }
+        cropped
+    }
+
+    /// State of the cell in column `x` and row `y` (counted from the top).
+    pub fn get(&self, x: u32, y: u32) -> u32 {
+        self.cells[(y * self.width + x) as usize]
+    }
+
+    /// Sets a cell, growing the pattern if it's outside the current bounds.
+    pub fn set(&mut self, x: u32, y: u32, state: u32) {
+        if x >= self.width || y >= self.height {
+            self.grow(self.width.max(x + 1), self.height.max(y + 1));
+        }
+        self.cells[(y * self.width + x) as usize] = state;
+    }
+
+    /// Enlarges the pattern to `width` x `height`, keeping its cells in the top left corner.
+    /// Panics like [`Pattern::new`] when that's too large.
+    fn grow(&mut self, width: u32, height: u32) {
+        let Pattern { mut cells, .. } = Pattern::new(width, height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                cells[(y * width + x) as usize] = self.get(x, y);
+            }
+        }
+        self.width = width;
+        self.height = height;
+        self.cells = cells;
+    }
+
+    /// Builds the smallest pattern containing all the given live cells. Coordinates can be
+    /// negative, with y increasing downwards. Returns `None` if the cells are spread over more
+    /// than [`MAX_PATTERN_CELLS`].
+    pub fn from_coordinates(live: &[(i64, i64)]) -> Option<Pattern> {
+        let Some(&(first_x, first_y)) = live.first() else {
+            return Some(Pattern::new(0, 0));
+        };
+        let (mut min_x, mut min_y) = (first_x, first_y);
+        let (mut max_x, mut max_y) = (first_x, first_y);
+        for &(x, y) in live {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let (width, height) = span(min_x, max_x).zip(span(min_y, max_y))?;
+        Pattern::cell_count(width as u64, height as u64)?;
+        let mut pattern = Pattern::new(width, height);
+        for &(x, y) in live {
+            pattern.set((x - min_x) as u32, (y - min_y) as u32, 1);
+        }
+        Some(pattern)
+    }
+
+    /// Parses a pattern in Golly/LifeWiki RLE format.
+    pub fn from_rle(source: &str) -> Result<Pattern, ParseError> {
+        rle::parse(source)
+    }
+
+    /// Parses a plaintext `.cells` pattern.
+    pub fn from_plaintext(source: &str) -> Result<Pattern, ParseError> {
+        plaintext::parse(source)
+    }
+
+    /// Parses a Life 1.05 or Life 1.06 pattern, depending on its `#Life` header line.
+    pub fn from_lif(source: &str) -> Result<Pattern, ParseError> {
+        match source.lines().next().map(str::trim) {
+            Some(lif::LIFE_105_HEADER) => lif::parse_105(source),
+            Some(lif::LIFE_106_HEADER) => lif::parse_106(source),
+            _ => Err(ParseError::new(
+                1,
+                1,
+                format!(
+                    "expected a {:?} or {:?} header",
+                    lif::LIFE_105_HEADER,
+                    lif::LIFE_106_HEADER
+                ),
+            )),
+        }
+    }
+
+    /// Encodes the pattern as RLE, including its rule and generation if they're known.
+    pub fn to_rle(&self) -> String {
+        rle::write(self)
+    }
+
+    pub fn save_rle(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_rle())
+    }
+
+    /// Reads a pattern file. The format is picked from the file extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Pattern, PatternError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("rle") => Ok(Pattern::from_rle(&source)?),
+            Some("cells") => Ok(Pattern::from_plaintext(&source)?),
+            Some("lif" | "life") => Ok(Pattern::from_lif(&source)?),
+            _ => Err(PatternError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    /// Writes the pattern into `board` (a `board_width` x `board_height` row-major board with
+    /// row 0 at the bottom). Cells that fall outside the board are dropped.
+    pub fn place(
+        &self,
+        board: &mut [u32],
+        board_width: u32,
+        board_height: u32,
+        placement: Placement,
+    ) {
+        let (left, top) = match placement {
+            Placement::Centered => (
+                (board_width as i64 - self.width as i64) / 2,
+                (board_height as i64 - self.height as i64) / 2,
+            ),
+            Placement::At { x, y } => (x, y),
+        };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let board_x = left + x as i64;
+                let board_y = top + y as i64;
+                if board_x < 0
+                    || board_y < 0
+                    || board_x >= board_width as i64
+                    || board_y >= board_height as i64
+                {
+                    continue;
+                }
+                let row = board_height as i64 - 1 - board_y;
+                board[(row * board_width as i64 + board_x) as usize] = self.get(x, y);
+            }
+        }
+    }
+}
+
+/// Number of cells from `min` to `max` inclusive, or `None` if that doesn't fit in a `u32`.
+pub(crate) fn span(min: i64, max: i64) -> Option<u32> {
+    u32::try_from(max as i128 - min as i128 + 1).ok()
+}
+
+/// A syntax error in a pattern file, with the 1-based position it was found at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(line: usize, column: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(io::Error),
+    Parse(ParseError),
+    UnknownFormat(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Io(e) => write!(f, "reading pattern failed: {e}"),
+            PatternError::Parse(e) => write!(f, "invalid pattern: {e}"),
+            PatternError::UnknownFormat(path) => {
+                write!(f, "can't tell the pattern format of {path}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<io::Error> for PatternError {
+    fn from(e: io::Error) -> Self {
+        PatternError::Io(e)
+    }
+}
+
+impl From<ParseError> for PatternError {
+    fn from(e: ParseError) -> Self {
+        PatternError::Parse(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::MAX_STATES;
+
+    #[test]
+    fn plaintext_counts_dead_rows_and_columns() {
+        let pattern = Pattern::from_plaintext("!Name: glider\n.O...\n..O\nOOO\n\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (5, 4));
+        assert_eq!(pattern.cells.iter().filter(|&&state| state == 1).count(), 5);
+        assert_eq!(pattern.get(1, 0), 1);
+    }
+
+    #[test]
+    fn far_apart_life_106_cells_are_an_error() {
+        for source in [
+            "#Life 1.06\n-2000000000 0\n2000000000 0\n",
+            "#Life 1.06\n0 -2000000000\n0 2000000000\n",
+        ] {
+            let error = Pattern::from_lif(source).unwrap_err();
+            assert_eq!((error.line, error.column), (3, 1));
+        }
+    }
+
+    #[test]
+    fn far_apart_life_105_blocks_are_an_error() {
+        let source = "#Life 1.05\n#P -9223372036854775807 0\n*\n#P 9223372036854775807 0\n.*\n";
+        let error = Pattern::from_lif(source).unwrap_err();
+        assert_eq!((error.line, error.column), (5, 2));
+    }
+
+    #[test]
+    fn oversized_rle_header_is_an_error() {
+        let error = Pattern::from_rle("#C huge\nx = 100000, y = 100000\no!\n").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 1));
+    }
+
+    #[test]
+    fn oversized_rle_runs_are_an_error() {
+        for body in ["4294967295o!", "3o$4294967295b67108864o!"] {
+            let error = Pattern::from_rle(&format!("x = 3, y = 2\n{body}\n")).unwrap_err();
+            assert_eq!(error.line, 2, "{body}");
+        }
+    }
+
+    #[test]
+    fn rle_runs_past_the_header_grow_the_pattern() {
+        let pattern = Pattern::from_rle("x = 2, y = 1\n2b3o$o!\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (5, 2));
+        assert_eq!(pattern.get(4, 0), 1);
+        assert_eq!(pattern.get(0, 1), 1);
+    }
+
+    #[test]
+    fn many_rle_rows_past_the_header_parse_quickly() {
+        // each row used to reallocate the whole pattern, taking minutes
+        let source = format!(
+            "x = 4000, y = 1
+{}!
+",
+            "o$".repeat(4000)
+        );
+        let start = std::time::Instant::now();
+        let pattern = Pattern::from_rle(&source).unwrap();
+        assert!(start.elapsed().as_secs() < 5, "took {:?}", start.elapsed());
+        assert_eq!((pattern.width, pattern.height), (4000, 4000));
+        assert_eq!(pattern.get(0, 3999), 1);
+        assert_eq!(pattern.get(1, 3999), 0);
+    }
+
+    #[test]
+    fn rle_encodes_the_highest_generations_state() {
+        let mut pattern = Pattern::new(2, 1);
+        pattern.set(0, 0, MAX_STATES - 1);
+        pattern.set(1, 0, 25);
+        let rle = pattern.to_rle();
+        assert!(rle.is_ascii(), "{rle}");
+        assert!(rle.contains("yOpA!"), "{rle}");
+        assert_eq!(Pattern::from_rle(&rle).unwrap().cells, pattern.cells);
+    }
+
+    #[test]
+    fn boards_round_trip_through_rle() {
+        // a glider in the middle of a 5x4 board, bottom row first
+        #[rustfmt::skip]
+        let board = [
+            0, 0, 0, 0, 0,
+            0, 1, 1, 1, 0,
+            0, 0, 0, 1, 0,
+            0, 0, 1, 0, 0,
+        ];
+        let pattern = Pattern {
+            rule: Some(Rule::HIGH_LIFE),
+            generation: Some(42),
+            ..Pattern::from_board(&board, 5, 4).cropped()
+        };
+        let rle = pattern.to_rle();
+        assert_eq!(
+            rle,
+            "#CXRLE Pos=0,0 Gen=42\nx = 3, y = 3, rule = B36/S23\nbo$2bo$3o!\n"
+        );
+        assert_eq!(Pattern::from_rle(&rle), Ok(pattern));
+    }
+
+    #[test]
+    fn long_rle_lines_are_wrapped() {
+        let mut pattern = Pattern::new(300, 3);
+        for x in (0..300).step_by(3) {
+            pattern.set(x, x % 3, 1);
+        }
+        let rle = pattern.to_rle();
+        assert!(rle.lines().count() > 3, "{rle}");
+        assert!(rle.lines().all(|line| line.len() <= 70), "{rle}");
+        assert_eq!(Pattern::from_rle(&rle), Ok(pattern));
+    }
+
+    #[test]
+    fn multi_state_patterns_round_trip_through_rle() {
+        let mut pattern = Pattern {
+            rule: Some(Rule::STAR_WARS.with_states(40)),
+            ..Pattern::new(4, 2)
+        };
+        for (x, y, state) in [(0, 0, 1), (1, 0, 2), (2, 0, 3), (3, 1, 30)] {
+            pattern.set(x, y, state);
+        }
+        let rle = pattern.to_rle();
+        assert_eq!(rle, "x = 4, y = 2, rule = B2/S345/C40\nABC$3.pF!\n");
+        assert_eq!(Pattern::from_rle(&rle), Ok(pattern));
+    }
+}
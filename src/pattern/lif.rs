@@ -0,0 +1,150 @@
+//! Life 1.05 and 1.06 `.lif` patterns: <https://conwaylife.com/wiki/Life_1.05> and
+//! <https://conwaylife.com/wiki/Life_1.06>
+
+use super::{span, ParseError, Pattern, MAX_PATTERN_CELLS};
+use crate::rule::Rule;
+
+pub(super) const LIFE_105_HEADER: &str = "#Life 1.05";
+pub(super) const LIFE_106_HEADER: &str = "#Life 1.06";
+
+/// Parses a list of live cell coordinates, one `x y` pair per line.
+pub(super) fn parse_106(source: &str) -> Result<Pattern, ParseError> {
+    let mut live = LiveCells::default();
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (x, y) = parse_position(line, 0, line_number)?;
+        live.push((x, y), line_number, 1)?;
+    }
+    Ok(live.into_pattern())
+}
+
+/// Parses blocks of `.`/`*` rows, each placed by the `#P x y` line before it.
+pub(super) fn parse_105(source: &str) -> Result<Pattern, ParseError> {
+    let mut live = LiveCells::default();
+    let mut rule = None;
+    let (mut block_x, mut block_y) = (0i64, 0i64);
+    let mut row = 0;
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        if let Some(rest) = line.strip_prefix("#P") {
+            (block_x, block_y) = parse_position(rest, 2, line_number)?;
+            row = 0;
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("#R") {
+            let column = 3 + rest.len() - rest.trim_start().len();
+            rule = Some(
+                rest.trim()
+                    .parse::<Rule>()
+                    .map_err(|e| ParseError::new(line_number, column, e.to_string()))?,
+            );
+            continue;
+        }
+        if line.starts_with("#N") {
+            rule = Some(Rule::CONWAY);
+            continue;
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        for (x, c) in line.trim_end().chars().enumerate() {
+            match c {
+                '.' => {}
+                '*' => live.push(
+                    (
+                        block_x.saturating_add(x as i64),
+                        block_y.saturating_add(row),
+                    ),
+                    line_number,
+                    x + 1,
+                )?,
+                _ => {
+                    return Err(ParseError::new(
+                        line_number,
+                        x + 1,
+                        format!("unexpected character {c:?}, expected '.' or '*'"),
+                    ))
+                }
+            }
+        }
+        row += 1;
+    }
+    Ok(Pattern {
+        rule,
+        ..live.into_pattern()
+    })
+}
+
+/// Live cell coordinates, along with the box around them so far.
+#[derive(Default)]
+struct LiveCells {
+    cells: Vec<(i64, i64)>,
+    min: (i64, i64),
+    max: (i64, i64),
+}
+
+impl LiveCells {
+    /// Adds a cell, failing at `line` and `column` if the pattern would get too large.
+    fn push(&mut self, (x, y): (i64, i64), line: usize, column: usize) -> Result<(), ParseError> {
+        let (min, max) = if self.cells.is_empty() {
+            ((x, y), (x, y))
+        } else {
+            (
+                (self.min.0.min(x), self.min.1.min(y)),
+                (self.max.0.max(x), self.max.1.max(y)),
+            )
+        };
+        let fits = span(min.0, max.0)
+            .zip(span(min.1, max.1))
+            .and_then(|(width, height)| Pattern::cell_count(width as u64, height as u64));
+        if fits.is_none() {
+            return Err(ParseError::new(
+                line,
+                column,
+                format!("cells are spread over more than {MAX_PATTERN_CELLS} cells"),
+            ));
+        }
+        (self.min, self.max) = (min, max);
+        self.cells.push((x, y));
+        Ok(())
+    }
+
+    fn into_pattern(self) -> Pattern {
+        Pattern::from_coordinates(&self.cells).expect("ERROR: Live cells were checked to fit.")
+    }
+}
+
+/// Parses the two whitespace separated coordinates in `text`, which starts `offset` characters
+/// into the line for error columns.
+fn parse_position(text: &str, offset: usize, line_number: usize) -> Result<(i64, i64), ParseError> {
+    let tokens: Vec<(usize, &str)> = text
+        .split_whitespace()
+        .map(|token| {
+            (
+                offset + token.as_ptr() as usize - text.as_ptr() as usize + 1,
+                token,
+            )
+        })
+        .collect();
+    if tokens.len() != 2 {
+        let column = tokens.get(2).map_or(offset + 1, |(column, _)| *column);
+        return Err(ParseError::new(
+            line_number,
+            column,
+            "expected two coordinates `x y`",
+        ));
+    }
+    let coordinate = |(column, token): (usize, &str)| {
+        token.parse::<i64>().map_err(|_| {
+            ParseError::new(
+                line_number,
+                column,
+                format!("expected a coordinate, found {token:?}"),
+            )
+        })
+    };
+    Ok((coordinate(tokens[0])?, coordinate(tokens[1])?))
+}
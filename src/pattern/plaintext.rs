@@ -0,0 +1,46 @@
+//! Plaintext `.cells` patterns: <https://conwaylife.com/wiki/Plaintext>
+
+use super::{ParseError, Pattern, MAX_PATTERN_CELLS};
+
+pub(super) fn parse(source: &str) -> Result<Pattern, ParseError> {
+    // check every row first, so the pattern is allocated once at its final size
+    let mut rows = Vec::new();
+    let mut width = 0;
+    for (i, line) in source.lines().enumerate() {
+        if line.starts_with('!') {
+            continue;
+        }
+        let row = line.trim_end();
+        if let Some((x, c)) = row
+            .chars()
+            .enumerate()
+            .find(|(_, c)| !matches!(c, '.' | 'O' | '*'))
+        {
+            return Err(ParseError::new(
+                i + 1,
+                x + 1,
+                format!("unexpected character {c:?}, expected '.' or 'O'"),
+            ));
+        }
+        // dead rows and trailing dead cells still count towards the size
+        width = width.max(row.chars().count() as u64);
+        rows.push(row);
+        if Pattern::cell_count(width, rows.len() as u64).is_none() {
+            return Err(ParseError::new(
+                i + 1,
+                1,
+                format!("pattern has more than {MAX_PATTERN_CELLS} cells"),
+            ));
+        }
+    }
+
+    let mut pattern = Pattern::new(width as u32, rows.len() as u32);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c != '.' {
+                pattern.set(x as u32, y as u32, 1);
+            }
+        }
+    }
+    Ok(pattern)
+}
@@ -0,0 +1,290 @@
+//! Run length encoded patterns, as used by Golly and the LifeWiki:
+//! <https://conwaylife.com/wiki/Run_Length_Encoded>
+
+use std::fmt::Write;
+
+use super::{ParseError, Pattern, MAX_PATTERN_CELLS};
+use crate::rule::Rule;
+
+/// Golly wraps RLE bodies at 70 characters.
+const MAX_LINE_LENGTH: usize = 70;
+
+pub(super) fn parse(source: &str) -> Result<Pattern, ParseError> {
+    let mut pattern: Option<Pattern> = None;
+    // wide enough that runs can't overflow them, they're checked before any cell is set
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut run_count: Option<u32> = None;
+    // multi-state patterns write states above 24 as a prefix letter p..y followed by A..X
+    let mut prefix: Option<char> = None;
+    let mut last_line = 0;
+    // live runs as (x, y, length, state), set once the final size is known so that rows past
+    // the header's don't reallocate the pattern one at a time
+    let mut runs: Vec<(u32, u32, u32, u32)> = Vec::new();
+    let (mut width, mut height) = (0u64, 0u64);
+
+    let mut generation = None;
+
+    'lines: for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        last_line = line_number;
+        let trimmed = line.trim();
+        if let Some(extended) = trimmed.strip_prefix("#CXRLE") {
+            generation = extended
+                .split_whitespace()
+                .find_map(|field| field.strip_prefix("Gen="))
+                .and_then(|gen| gen.parse().ok());
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if pattern.is_none() {
+            let header = parse_header(line, line_number)?;
+            (width, height) = (header.width as u64, header.height as u64);
+            pattern = Some(header);
+            continue;
+        }
+
+        for (j, c) in line.chars().enumerate() {
+            let column = j + 1;
+            if let Some(prefix) = prefix.filter(|_| !c.is_ascii_uppercase()) {
+                return Err(ParseError::new(
+                    line_number,
+                    column,
+                    format!("expected a state letter A-X after {prefix:?}"),
+                ));
+            }
+            let state = match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap();
+                    run_count = Some(
+                        run_count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|n| n.checked_add(digit))
+                            .ok_or_else(|| {
+                                ParseError::new(line_number, column, "run count is too large")
+                            })?,
+                    );
+                    continue;
+                }
+                'p'..='y' => {
+                    prefix = Some(c);
+                    continue;
+                }
+                ' ' | '\t' => continue,
+                '!' => break 'lines,
+                '$' => {
+                    y = y.saturating_add(run_count.take().unwrap_or(1) as u64);
+                    x = 0;
+                    continue;
+                }
+                'b' | '.' => 0,
+                'o' => 1,
+                'A'..='X' => {
+                    let high = prefix.take().map_or(0, |p| p as u32 - 'p' as u32 + 1);
+                    high * 24 + (c as u32 - 'A' as u32 + 1)
+                }
+                _ => {
+                    return Err(ParseError::new(
+                        line_number,
+                        column,
+                        format!("unexpected character {c:?}"),
+                    ))
+                }
+            };
+
+            let run = run_count.take().unwrap_or(1) as u64;
+            if state != 0 {
+                // cells past the header's size grow the pattern, up to the size limit
+                let end = x + run;
+                if Pattern::cell_count(end.max(width), (y + 1).max(height)).is_none() {
+                    return Err(ParseError::new(
+                        line_number,
+                        column,
+                        format!("run goes past the limit of {MAX_PATTERN_CELLS} cells"),
+                    ));
+                }
+                (width, height) = (end.max(width), (y + 1).max(height));
+                runs.push((x as u32, y as u32, run as u32, state));
+            }
+            x = x.saturating_add(run);
+        }
+    }
+
+    let mut pattern = pattern
+        .ok_or_else(|| ParseError::new(last_line + 1, 1, "missing `x = .., y = ..` header"))?;
+    if width > pattern.width as u64 || height > pattern.height as u64 {
+        pattern.grow(width as u32, height as u32);
+    }
+    for (x, y, run, state) in runs {
+        let start = (y * pattern.width + x) as usize;
+        pattern.cells[start..start + run as usize].fill(state);
+    }
+    Ok(Pattern {
+        generation,
+        ..pattern
+    })
+}
+
+/// Encodes `pattern` as RLE, using `b`/`o` for two state patterns and `.`/`A`.. otherwise.
+pub(super) fn write(pattern: &Pattern) -> String {
+    let mut rle = String::new();
+    if let Some(generation) = pattern.generation {
+        // Golly's extended RLE line, so the generation survives a round trip
+        writeln!(rle, "#CXRLE Pos=0,0 Gen={generation}").unwrap();
+    }
+    write!(rle, "x = {}, y = {}", pattern.width, pattern.height).unwrap();
+    if let Some(rule) = pattern.rule {
+        write!(rle, ", rule = {rule}").unwrap();
+    }
+    rle.push('\n');
+
+    let multi_state = pattern.cells.iter().any(|&state| state > 1);
+    let mut line = String::new();
+    let mut last_row = None;
+    for y in 0..pattern.height {
+        let row: Vec<u32> = (0..pattern.width).map(|x| pattern.get(x, y)).collect();
+        let Some(end) = row.iter().rposition(|&state| state != 0) else {
+            continue;
+        };
+        let skipped_rows = match last_row {
+            Some(last_row) => y - last_row,
+            None => y,
+        };
+        if skipped_rows > 0 {
+            push_token(&mut line, &mut rle, skipped_rows, "$");
+        }
+        last_row = Some(y);
+
+        let mut x = 0;
+        while x <= end {
+            let state = row[x];
+            let run = row[x..=end].iter().take_while(|&&s| s == state).count();
+            push_token(
+                &mut line,
+                &mut rle,
+                run as u32,
+                &state_tag(state, multi_state),
+            );
+            x += run;
+        }
+    }
+    push_token(&mut line, &mut rle, 1, "!");
+    rle.push_str(&line);
+    rle.push('\n');
+    rle
+}
+
+/// Appends `count` repetitions of `tag` to `line`, first moving `line` into `rle` if it would
+/// get too long.
+fn push_token(line: &mut String, rle: &mut String, count: u32, tag: &str) {
+    let token = if count > 1 {
+        format!("{count}{tag}")
+    } else {
+        tag.to_string()
+    };
+    if line.len() + token.len() > MAX_LINE_LENGTH {
+        rle.push_str(line);
+        rle.push('\n');
+        line.clear();
+    }
+    line.push_str(&token);
+}
+
+/// Highest state RLE can encode: `A`..`X` with no prefix or one of `p`..`y`.
+const MAX_RLE_STATE: u32 = 24 * 11;
+
+/// Panics on states above [`MAX_RLE_STATE`], which no rule has (see [`MAX_STATES`]).
+///
+/// [`MAX_STATES`]: crate::rule::MAX_STATES
+fn state_tag(state: u32, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (state, true) => {
+            assert!(
+                state <= MAX_RLE_STATE,
+                "ERROR: RLE can't encode states above MAX_RLE_STATE."
+            );
+            let high = (state - 1) / 24;
+            let low = char::from(b'A' + ((state - 1) % 24) as u8);
+            if high == 0 {
+                low.to_string()
+            } else {
+                format!("{}{low}", char::from(b'p' + (high - 1) as u8))
+            }
+        }
+    }
+}
+
+/// Parses the `x = 3, y = 3, rule = B3/S23` line into an empty pattern of that size.
+fn parse_header(line: &str, line_number: usize) -> Result<Pattern, ParseError> {
+    let mut width = None;
+    let mut height = None;
+    let mut rule = None;
+
+    let mut offset = 0;
+    for field in line.split(',') {
+        let column = offset + 1;
+        offset += field.len() + 1;
+
+        let Some((key, value)) = field.split_once('=') else {
+            if rule.is_some() {
+                // the second half of a bounded grid size after the rule
+                continue;
+            }
+            return Err(ParseError::new(
+                line_number,
+                column,
+                "expected `key = value` in header",
+            ));
+        };
+        let value_column = column + key.len() + 1 + (value.len() - value.trim_start().len());
+        let value = value.trim();
+        match key.trim() {
+            "x" | "y" => {
+                let size: u32 = value.parse().map_err(|_| {
+                    ParseError::new(line_number, value_column, format!("invalid size {value:?}"))
+                })?;
+                if key.trim() == "x" {
+                    width = Some(size);
+                } else {
+                    height = Some(size);
+                }
+            }
+            "rule" => {
+                // Golly appends the bounded grid it was saved on, like B3/S23:T100,100
+                let value = value.split(':').next().unwrap_or(value);
+                rule = Some(
+                    value
+                        .parse::<Rule>()
+                        .map_err(|e| ParseError::new(line_number, value_column, e.to_string()))?,
+                );
+            }
+            // anything else, like Golly's `generation`, doesn't affect the cells
+            _ => {}
+        }
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => {
+            if Pattern::cell_count(width as u64, height as u64).is_none() {
+                return Err(ParseError::new(
+                    line_number,
+                    1,
+                    format!("{width}x{height} pattern has more than {MAX_PATTERN_CELLS} cells"),
+                ));
+            }
+            Ok(Pattern {
+                rule,
+                ..Pattern::new(width, height)
+            })
+        }
+        _ => Err(ParseError::new(
+            line_number,
+            1,
+            "header needs both `x = ` and `y = `",
+        )),
+    }
+}
@@ -1,37 +1,330 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{TextureFormat, VertexAttribute, VertexBufferLayout};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Pod, Zeroable)]
 pub struct Point2 {
-    x: f32,
-    y: f32,
+    pub x: f32,
+    pub y: f32,
 }
 
 impl Point2 {
-    pub fn new(x: f32, y: f32) -> Point2 {
+    pub const fn new(x: f32, y: f32) -> Point2 {
         Point2 { x, y }
     }
+
+    pub fn distance(self, other: Point2) -> f32 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
 }
 
-pub struct Color(f32, f32, f32, f32);
+/// RGBA colour in linear RGB with straight alpha, laid out like a `vec4f`.
+///
+/// The shaders work in linear RGB and encode to sRGB on the way out, either in the target
+/// itself for `*Srgb` formats or in the fragment shader for the others, so a colour looks the
+/// same on any target. Blending happens before that encoding on non-sRGB targets though, so
+/// translucent shapes come out slightly different there.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Pod, Zeroable)]
+pub struct Color(pub f32, pub f32, pub f32, pub f32);
 
 impl Color {
-    pub fn from_srgba(r: f32, g: f32, b: f32, a: f32) -> Color {
+    pub const fn linear(r: f32, g: f32, b: f32, a: f32) -> Color {
         Color(r, g, b, a)
     }
+
+    /// From sRGB encoded components in `0..=1`, the way colour pickers and CSS give them.
+    /// Alpha is linear either way.
+    pub fn from_srgba(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+    }
+
+    /// From 8-bit sRGB components.
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Color {
+        let unit = |c: u8| c as f32 / 255.;
+        Color::from_srgba(unit(r), unit(g), unit(b), unit(a))
+    }
+
+    /// From a hex string like `#0a0c1c`: `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`, with or
+    /// without the `#`.
+    pub fn from_hex(hex: &str) -> Result<Color, ParseColorError> {
+        let error = || ParseColorError(hex.to_string());
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !digits.is_ascii() {
+            return Err(error());
+        }
+        let components: Vec<u8> = match digits.len() {
+            3 | 4 => digits
+                .chars()
+                .map(|c| c.to_digit(16).map(|d| d as u8 * 0x11))
+                .collect::<Option<_>>()
+                .ok_or_else(error)?,
+            6 | 8 => (0..digits.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+                .collect::<Option<_>>()
+                .ok_or_else(error)?,
+            _ => return Err(error()),
+        };
+        let alpha = components.get(3).copied().unwrap_or(0xff);
+        Ok(Color::from_rgba8(
+            components[0],
+            components[1],
+            components[2],
+            alpha,
+        ))
+    }
+
+    /// From sRGB hue in degrees, and saturation and lightness in `0..=1`, as in CSS `hsl()`.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Color {
+        let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+        from_hue(hue, chroma, lightness - chroma / 2., alpha)
+    }
+
+    /// From sRGB hue in degrees, and saturation and value in `0..=1`.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Color {
+        let chroma = value * saturation;
+        from_hue(hue, chroma, value - chroma, alpha)
+    }
+
+    /// The sRGB encoded components in `0..=1`, alpha unchanged.
+    pub fn to_srgba(self) -> [f32; 4] {
+        [
+            linear_to_srgb(self.0),
+            linear_to_srgb(self.1),
+            linear_to_srgb(self.2),
+            self.3,
+        ]
+    }
+
+    /// As a clear colour for a target of `format`, which takes linear values if it's an sRGB
+    /// format and already encoded ones otherwise.
+    pub fn to_wgpu(self, format: TextureFormat) -> wgpu::Color {
+        let [r, g, b, a] = if format.is_srgb() {
+            [self.0, self.1, self.2, self.3]
+        } else {
+            self.to_srgba()
+        };
+        wgpu::Color {
+            r: r as f64,
+            g: g as f64,
+            b: b as f64,
+            a: a as f64,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Color::from_hex(s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(pub String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid colour {:?}, expected hex like #0a0c1c or #0a0c1cff",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+/// The sRGB transfer function, decoding one component.
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The sRGB transfer function, encoding one component, like `encode_output` in the shaders.
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// The sRGB colour with `hue` and `chroma`, plus `lightness` on every component.
+fn from_hue(hue: f32, chroma: f32, lightness: f32, alpha: f32) -> Color {
+    let sector = hue.rem_euclid(360.) / 60.;
+    let x = chroma * (1. - (sector % 2. - 1.).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    Color::from_srgba(r + lightness, g + lightness, b + lightness, alpha)
 }
 
+/// A vertex as the shaders take it: `coords` at `@location(0)` and `color` at `@location(1)`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Pod, Zeroable)]
 pub struct Vertex {
-    coords: Point2,
-    color: Color,
+    pub coords: Point2,
+    pub color: Color,
+}
+
+impl Vertex {
+    const ATTRIBUTES: [VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+
+    /// How a buffer of vertices is laid out, for the vertex state of a pipeline.
+    pub const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &Vertex::ATTRIBUTES,
+    };
+
+    pub const fn new(coords: Point2, color: Color) -> Vertex {
+        Vertex { coords, color }
+    }
+}
+
+/// A vertex of the draw list as `shapes.wgsl` takes it: a [`Vertex`] plus, at
+/// `@location(2)`, its barycentric distance to each outline edge of its triangle. Edges inside
+/// the shape get 1 at every corner, so they never fade under analytic antialiasing.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Pod, Zeroable)]
+pub struct ShapeVertex {
+    pub vertex: Vertex,
+    pub edges: [f32; 3],
+}
+
+impl ShapeVertex {
+    const ATTRIBUTES: [VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4, 2 => Float32x3];
+
+    /// How a buffer of shape vertices is laid out, for the vertex state of a pipeline.
+    pub const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: std::mem::size_of::<ShapeVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &ShapeVertex::ATTRIBUTES,
+    };
 }
 
+/// Colour of live cells.
+pub const CELL_COLOR: Color = Color::linear(0.8, 0.8, 0.5, 1.);
+
+/// The quad drawn for every cell, spanning -1..1 with a gap to the next cell.
 //SHOULD BE IN CLOCKWISE ORDER
-pub const VERTICES: &[f32; 8] = &[
-    // Triangle 1
-    -0.8, -0.8, //
-    0.8, -0.8, //
-    0.8, 0.8, //
-    -0.8, 0.8, //
+pub const CELL_VERTICES: &[Vertex; 4] = &[
+    Vertex::new(Point2::new(-0.8, -0.8), CELL_COLOR),
+    Vertex::new(Point2::new(0.8, -0.8), CELL_COLOR),
+    Vertex::new(Point2::new(0.8, 0.8), CELL_COLOR),
+    Vertex::new(Point2::new(-0.8, 0.8), CELL_COLOR),
 ];
 
 pub const INDICES: &[u16; 6] = &[0, 1, 2, 2, 3, 0];
 
-pub const GRID_SIZE: f32 = 32.;
-pub const UNIFORM_ARRAY: &[f32; 2] = &[GRID_SIZE, GRID_SIZE];
+/// Board size in cells (width, height) used when none is given.
+pub const DEFAULT_GRID_SIZE: (u32, u32) = (32, 32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn assert_close(actual: Color, expected: Color) {
+        let Color(r, g, b, a) = actual;
+        let Color(er, eg, eb, ea) = expected;
+        let close = [(r, er), (g, eg), (b, eb), (a, ea)]
+            .iter()
+            .all(|(x, y)| (x - y).abs() < 1e-4);
+        assert!(close, "{actual:?} instead of {expected:?}");
+    }
+
+    #[test]
+    fn srgb_and_linear_convert_both_ways() {
+        assert!((srgb_to_linear(0.5) - 0.2140).abs() < 1e-4);
+        assert!((linear_to_srgb(0.2140) - 0.5).abs() < 1e-4);
+        // the linear segment near black
+        assert_eq!(srgb_to_linear(0.04), 0.04 / 12.92);
+        for i in 0..=100 {
+            let c = i as f32 / 100.;
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-5, "{c}");
+        }
+        let color = Color::from_srgba(0.1, 0.5, 0.9, 0.3);
+        let [r, g, b, a] = color.to_srgba();
+        assert_close(Color(r, g, b, a), Color(0.1, 0.5, 0.9, 0.3));
+    }
+
+    #[test]
+    fn parses_hex_of_every_length() {
+        let red = Color::linear(1., 0., 0., 1.);
+        for hex in ["#f00", "f00", "#f00f", "#ff0000", "#FF0000ff"] {
+            assert_close(Color::from_hex(hex).unwrap(), red);
+        }
+        assert_close(
+            "#ffffff80".parse().unwrap(),
+            Color::linear(1., 1., 1., 128. / 255.),
+        );
+        assert_close(
+            "#808080".parse().unwrap(),
+            Color::from_srgba(128. / 255., 128. / 255., 128. / 255., 1.),
+        );
+        for hex in ["", "#", "#ff", "#fffff", "#ggg", "#ff00zz", "#ffé0"] {
+            assert_eq!(
+                Color::from_hex(hex),
+                Err(ParseColorError(hex.to_string())),
+                "{hex:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn hsl_and_hsv_match_hex() {
+        for (hue, hex) in [
+            (0., "#ff0000"),
+            (60., "#ffff00"),
+            (120., "#00ff00"),
+            (180., "#00ffff"),
+            (240., "#0000ff"),
+            (300., "#ff00ff"),
+            (360., "#ff0000"),
+        ] {
+            let expected = Color::from_hex(hex).unwrap();
+            assert_close(Color::from_hsl(hue, 1., 0.5, 1.), expected);
+            assert_close(Color::from_hsv(hue, 1., 1., 1.), expected);
+        }
+        let grey = Color::from_hex("#808080").unwrap();
+        assert_close(Color::from_hsl(200., 0., 128. / 255., 1.), grey);
+        assert_close(Color::from_hsv(200., 0., 128. / 255., 1.), grey);
+        // #336699 is hsl(210, 50%, 40%) and hsv(210, 66.7%, 60%)
+        let blue = Color::from_hex("#336699").unwrap();
+        assert_close(Color::from_hsl(210., 0.5, 0.4, 1.), blue);
+        assert_close(Color::from_hsv(210., 2. / 3., 0.6, 1.), blue);
+    }
+
+    #[test]
+    fn clear_colours_are_encoded_only_for_non_srgb_targets() {
+        let color = Color::from_srgba(0.5, 0.25, 1., 0.5);
+        let srgb = color.to_wgpu(TextureFormat::Rgba8UnormSrgb);
+        assert!((srgb.r - color.0 as f64).abs() < 1e-6);
+        let unorm = color.to_wgpu(TextureFormat::Bgra8Unorm);
+        for (actual, expected) in [
+            (unorm.r, 0.5),
+            (unorm.g, 0.25),
+            (unorm.b, 1.),
+            (unorm.a, 0.5),
+        ] {
+            assert!((actual - expected).abs() < 1e-5, "{unorm:?}");
+        }
+    }
+}
@@ -1,123 +1,1637 @@
-use std::{iter, primitive};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::iter;
+use std::path::Path;
+use std::str::FromStr;
 
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{
-    Adapter, BindGroup, Buffer, BufferSlice, Color, CommandEncoderDescriptor,
-    ComputePassDescriptor, Device, Operations, Queue, RenderPassColorAttachment,
-    RenderPassDescriptor, RequestDeviceError, Surface, SurfaceConfiguration, TextureViewDescriptor,
+    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, Buffer, CommandEncoder,
+    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor, Device, Extent3d,
+    Operations, Queue, RenderPass, RenderPassColorAttachment, RenderPassDescriptor,
+    RequestDeviceError, ShaderStages, Surface, SurfaceConfiguration, Texture, TextureFormat,
+    TextureView, TextureViewDescriptor,
 };
 use winit::dpi::PhysicalSize;
 
-use crate::primitives::GRID_SIZE;
+use crate::boundary::Boundary;
+use crate::camera::Camera;
+use crate::draw::DrawList;
+use crate::pattern::{Pattern, Placement};
+use crate::primitives::{Color, ShapeVertex, Vertex};
+use crate::rule::Rule;
+use crate::seed::Seeding;
+use crate::step_rate::StepRate;
+use crate::window::request_device;
+
+/// Format of the offscreen texture used by headless contexts.
+pub const HEADLESS_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// Most generations encoded into one command buffer.
+pub const STEPS_PER_SUBMIT: u64 = 1024;
+
+/// How edges of cells and shapes are smoothed, chosen when the context is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Antialiasing {
+    /// Hard pixel edges.
+    None,
+    /// 4x multisampling, resolved into the target every frame. Smooths everything, at the cost
+    /// of a multisampled copy of the target.
+    #[default]
+    Msaa4x,
+    /// Coverage computed in the fragment shader, from each cell's signed distance and from
+    /// each shape's distance to its outline. Nearly free, but edges fade inwards only.
+    Analytic,
+}
+
+impl Antialiasing {
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            Antialiasing::Msaa4x => 4,
+            Antialiasing::None | Antialiasing::Analytic => 1,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Antialiasing::None => "none",
+            Antialiasing::Msaa4x => "msaa",
+            Antialiasing::Analytic => "analytic",
+        }
+    }
+}
+
+impl fmt::Display for Antialiasing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAntialiasingError(pub String);
+
+impl fmt::Display for ParseAntialiasingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown antialiasing {:?}, expected one of none, msaa, analytic",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseAntialiasingError {}
+
+impl FromStr for Antialiasing {
+    type Err = ParseAntialiasingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "off" => Ok(Antialiasing::None),
+            "msaa" | "msaa4x" => Ok(Antialiasing::Msaa4x),
+            "analytic" | "sdf" => Ok(Antialiasing::Analytic),
+            _ => Err(ParseAntialiasingError(s.to_string())),
+        }
+    }
+}
+
+/// What a frame gets drawn into.
+enum RenderTarget<'a> {
+    /// The window's swapchain, presented after every frame.
+    Surface {
+        surface: Surface<'a>,
+        config: SurfaceConfiguration,
+    },
+    /// An offscreen texture, for running without a display.
+    Texture(Texture),
+}
 
 pub struct RenderContext<'a> {
-    surface: Surface<'a>,
+    target: RenderTarget<'a>,
     device: Device,
     queue: Queue,
-    config: SurfaceConfiguration,
+    format: TextureFormat,
     size: winit::dpi::PhysicalSize<u32>,
     render_pipeline: wgpu::RenderPipeline,
     compute_pipeline: wgpu::ComputePipeline,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
     uniform_buffer: Buffer,
+    rule_buffer: Buffer,
+    boundary_buffer: Buffer,
+    camera_buffer: Buffer,
+    shape_pipeline: wgpu::RenderPipeline,
+    shape_bind_group: BindGroup,
+    /// Dynamic buffers holding the shapes of the last frame, grown as needed. Analytic
+    /// antialiasing draws them unindexed, from [`DrawList::shape_vertices`].
+    shape_vertex_buffer: Buffer,
+    shape_index_buffer: Buffer,
+    /// Indices to draw, or vertices when they're unindexed.
+    shape_count: u32,
+    draw_list: DrawList,
+    /// Colour the target is cleared to before the cells are drawn.
+    background: Color,
+    antialiasing: Antialiasing,
+    /// Multisampled colour target that frames are drawn into and resolved from, when using MSAA.
+    msaa_view: Option<TextureView>,
     storage_buffers: [Buffer; 2],
     bind_groups: [BindGroup; 2],
+    grid_size: (u32, u32),
+    camera: Camera,
+    rule: Rule,
+    boundary: Boundary,
+    seeding: Seeding,
+    step_rate: StepRate,
+    /// Frames rendered so far, to spread fractional step rates over frames.
+    frame: u64,
     step: u8,
+    generation: u64,
 }
 
 impl<'a> RenderContext<'a> {
+    /// Creates a context that renders to an already configured window surface. Fails if the
+    /// board doesn't fit in the device's storage buffers.
     pub fn new(
         surface: Surface<'a>,
         device: Device,
         queue: Queue,
         config: SurfaceConfiguration,
         size: winit::dpi::PhysicalSize<u32>,
-        render_pipeline: wgpu::RenderPipeline,
-        compute_pipeline: wgpu::ComputePipeline,
-        vertex_buffer: Buffer,
-        index_buffer: Buffer,
-        uniform_buffer: Buffer,
-        storage_buffers: [Buffer; 2],
-        bind_groups: [BindGroup; 2],
-    ) -> RenderContext<'_> {
-        RenderContext {
-            surface,
+        grid_size: (u32, u32),
+        antialiasing: Antialiasing,
+    ) -> Result<RenderContext<'a>, GridSizeError> {
+        let format = config.format;
+        Self::with_target(
+            RenderTarget::Surface { surface, config },
+            device,
+            queue,
+            format,
+            size,
+            grid_size,
+            antialiasing,
+        )
+    }
+
+    fn with_target(
+        target: RenderTarget<'a>,
+        device: Device,
+        queue: Queue,
+        format: TextureFormat,
+        size: PhysicalSize<u32>,
+        grid_size: (u32, u32),
+        antialiasing: Antialiasing,
+    ) -> Result<RenderContext<'a>, GridSizeError> {
+        check_grid_size(&device, grid_size)?;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+        });
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Game of life simulation"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("compute.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // only the compute shader writes the next generation, so the vertex stage doesn't
+                // need Features::VERTEX_WRITABLE_STORAGE (which software adapters lack)
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout], // even though there are two bind groups, only one is used at a time so only one layout is necessary
+            push_constant_ranges: &[],
+        });
+
+        let output_constants = output_constants(format);
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::LAYOUT],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: match antialiasing {
+                    Antialiasing::Analytic => "fs_main_analytic_aa",
+                    Antialiasing::None | Antialiasing::Msaa4x => "fs_main",
+                },
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    // analytic coverage comes out as alpha at the cell edges
+                    blend: Some(match antialiasing {
+                        Antialiasing::Analytic => wgpu::BlendState::ALPHA_BLENDING,
+                        Antialiasing::None | Antialiasing::Msaa4x => wgpu::BlendState {
+                            color: wgpu::BlendComponent::REPLACE,
+                            alpha: wgpu::BlendComponent::REPLACE,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &output_constants,
+                    ..Default::default()
+                },
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
+                // or Features::POLYGON_MODE_POINT
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: antialiasing.sample_count(),
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            // If the pipeline will be used with a multiview render pass, this
+            // indicates how many array layers the attachments will have.
+            multiview: None,
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &compute_shader,
+            entry_point: "compute_main",
+            compilation_options: Default::default(),
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(crate::primitives::CELL_VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(crate::primitives::INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(&grid_uniform(grid_size)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let rule = Rule::default();
+        let rule_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rule Buffer"),
+            contents: bytemuck::cast_slice(&rule.to_uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let boundary = Boundary::default();
+        let boundary_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Boundary Buffer"),
+            contents: bytemuck::bytes_of(&(boundary as u32)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera = Camera::fit(grid_size, size);
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&camera.to_uniform(size)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let (shape_pipeline, shape_bind_group) =
+            create_shape_pipeline(&device, format, antialiasing, &camera_buffer);
+        let shape_vertex_buffer = create_shape_buffer(&device, wgpu::BufferUsages::VERTEX);
+        let shape_index_buffer = create_shape_buffer(&device, wgpu::BufferUsages::INDEX);
+
+        let msaa_view = create_msaa_view(&device, format, size, antialiasing);
+
+        let seeding = Seeding::random();
+        let cell_state_array = seeding.generate(grid_size.0, grid_size.1);
+
+        let (storage_buffers, bind_groups) = create_cell_bindings(
+            &device,
+            &render_pipeline.get_bind_group_layout(0),
+            &uniform_buffer,
+            &rule_buffer,
+            &boundary_buffer,
+            &camera_buffer,
+            &cell_state_array,
+        );
+
+        Ok(RenderContext {
+            target,
             device,
             queue,
-            config,
+            format,
             size,
             render_pipeline,
             compute_pipeline,
             vertex_buffer,
             index_buffer,
             uniform_buffer,
+            rule_buffer,
+            boundary_buffer,
+            camera_buffer,
+            shape_pipeline,
+            shape_bind_group,
+            shape_vertex_buffer,
+            shape_index_buffer,
+            shape_count: 0,
+            draw_list: DrawList::new(),
+            background: Color::from_rgba8(0x0a, 0x0c, 0x1c, 0xff),
+            antialiasing,
+            msaa_view,
             storage_buffers,
             bind_groups,
+            grid_size,
+            camera,
+            rule,
+            boundary,
+            seeding,
+            step_rate: StepRate::default(),
+            frame: 0,
             step: 0,
-        }
+            generation: 0,
+        })
     }
 
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            match &mut self.target {
+                RenderTarget::Surface { surface, config } => {
+                    config.width = new_size.width;
+                    config.height = new_size.height;
+                    surface.configure(&self.device, config);
+                }
+                RenderTarget::Texture(texture) => {
+                    *texture = create_target_texture(&self.device, self.format, new_size);
+                }
+            }
+            self.msaa_view =
+                create_msaa_view(&self.device, self.format, new_size, self.antialiasing);
+            // the camera keeps its zoom, but its clip space scale depends on the target size
+            self.write_camera();
+        }
+    }
+
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    /// Moves the view onto the board.
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+        self.write_camera();
+    }
+
+    /// Resets the view to show the whole board.
+    pub fn fit_camera(&mut self) {
+        self.set_camera(Camera::fit(self.grid_size, self.size));
+    }
+
+    fn write_camera(&self) {
+        self.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&self.camera.to_uniform(self.size)),
+        );
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn background(&self) -> Color {
+        self.background
+    }
+
+    pub fn set_background(&mut self, color: Color) {
+        self.background = color;
+    }
+
+    pub fn antialiasing(&self) -> Antialiasing {
+        self.antialiasing
+    }
+
+    /// Width and height of the board in cells.
+    pub fn grid_size(&self) -> (u32, u32) {
+        self.grid_size
+    }
+
+    /// Changes the board to `width` x `height` cells, rebuilding the storage buffers and bind
+    /// groups. Cells inside both the old and the new board keep their state, new ones start dead.
+    pub fn resize_grid(&mut self, width: u32, height: u32) -> Result<(), GridSizeError> {
+        if (width, height) == self.grid_size {
+            return Ok(());
+        }
+        check_grid_size(&self.device, (width, height))?;
+        let (old_width, old_height) = self.grid_size;
+        let old_cells = self.read_cells();
+        let mut cells = vec![0u32; (width * height) as usize];
+        for y in 0..height.min(old_height) {
+            for x in 0..width.min(old_width) {
+                cells[(y * width + x) as usize] = old_cells[(y * old_width + x) as usize];
+            }
+        }
+
+        self.grid_size = (width, height);
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&grid_uniform(self.grid_size)),
+        );
+        (self.storage_buffers, self.bind_groups) = create_cell_bindings(
+            &self.device,
+            &self.render_pipeline.get_bind_group_layout(0),
+            &self.uniform_buffer,
+            &self.rule_buffer,
+            &self.boundary_buffer,
+            &self.camera_buffer,
+            &cells,
+        );
+        self.fit_camera();
+        Ok(())
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Switches the rule the compute shader steps with, taking effect from the next generation.
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.queue.write_buffer(
+            &self.rule_buffer,
+            0,
+            bytemuck::cast_slice(&rule.to_uniform()),
+        );
+    }
+
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    /// Changes what lies beyond the edges of the board, taking effect from the next generation.
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+        self.queue.write_buffer(
+            &self.boundary_buffer,
+            0,
+            bytemuck::bytes_of(&(boundary as u32)),
+        );
+    }
+
+    /// How the board was last randomly filled, so the same start can be reproduced.
+    pub fn seeding(&self) -> Seeding {
+        self.seeding
+    }
+
+    /// Replaces the board with one generated from `seeding` and restarts the generation count.
+    pub fn reseed(&mut self, seeding: Seeding) {
+        let (width, height) = self.grid_size;
+        self.write_cells(&seeding.generate(width, height));
+        self.seeding = seeding;
+        self.generation = 0;
+    }
+
+    /// Kills every cell and restarts the generation count.
+    pub fn clear(&mut self) {
+        let (width, height) = self.grid_size;
+        self.write_cells(&vec![0; (width * height) as usize]);
+        self.generation = 0;
+    }
+
+    /// Index of the storage buffer holding the newest generation, i.e. the one the next compute
+    /// pass reads from. Bind group `i` reads buffer `i` and writes the other one, and each step
+    /// advances `step` before dispatching, so that's the buffer after `step`.
+    fn current_index(&self) -> usize {
+        ((self.step + 1) % 2) as usize
+    }
+
+    fn current_cells(&self) -> &Buffer {
+        &self.storage_buffers[self.current_index()]
+    }
+
+    /// Downloads the current generation, one `u32` per cell in row-major order.
+    pub fn read_cells(&self) -> Vec<u32> {
+        let cells = self.current_cells();
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell readback buffer"),
+            size: cells.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Cell Readback Encoder"),
+            });
+        encoder.copy_buffer_to_buffer(cells, 0, &staging_buffer, 0, cells.size());
+        self.queue.submit(iter::once(encoder.finish()));
+
+        bytemuck::cast_slice(&read_buffer(&self.device, &staging_buffer)).to_vec()
+    }
+
+    /// How many generations have been computed since the context was created or a pattern was
+    /// loaded.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Number of live cells in the current generation (refractory Generations states count as
+    /// dead).
+    pub fn population(&self) -> u64 {
+        self.read_cells()
+            .iter()
+            .filter(|&&state| state == 1)
+            .count() as u64
+    }
+
+    /// Downloads the current generation as a pattern cropped to its live cells, tagged with the
+    /// active rule and generation count, ready for [`Pattern::save_rle`].
+    pub fn to_pattern(&self) -> Pattern {
+        let (width, height) = self.grid_size;
+        let board = Pattern::from_board(&self.read_cells(), width, height);
+        Pattern {
+            rule: Some(self.rule),
+            generation: Some(self.generation),
+            ..board.cropped()
+        }
+    }
+
+    /// Downloads the current generation packed into a bitset: bit `i % 64` of word `i / 64`
+    /// is set when cell `i` is alive (refractory Generations states count as dead).
+    pub fn read_cells_bitset(&self) -> Vec<u64> {
+        let cells = self.read_cells();
+        let mut bits = vec![0u64; cells.len().div_ceil(64)];
+        for (i, _) in cells.iter().enumerate().filter(|(_, state)| **state == 1) {
+            bits[i / 64] |= 1 << (i % 64);
         }
+        bits
     }
 
+    /// Replaces the board with `cells`, one `u32` per cell in row-major order. The next
+    /// step starts from this state.
+    pub fn write_cells(&mut self, cells: &[u32]) {
+        let (width, height) = self.grid_size();
+        assert_eq!(
+            cells.len(),
+            (width * height) as usize,
+            "ERROR: Cell state doesn't match the {width}x{height} grid."
+        );
+        // both buffers are written so the state is also what gets drawn if nothing steps
+        for buffer in &self.storage_buffers {
+            self.queue
+                .write_buffer(buffer, 0, bytemuck::cast_slice(cells));
+        }
+    }
+
+    /// Clears the board and places `pattern` on it, switching to the pattern's rule if it has
+    /// one. The generation count restarts from the pattern's.
+    pub fn load_pattern(&mut self, pattern: &Pattern, placement: Placement) {
+        if let Some(rule) = pattern.rule {
+            self.set_rule(rule);
+        }
+        self.generation = pattern.generation.unwrap_or(0);
+        let (width, height) = self.grid_size;
+        let mut cells = vec![0u32; (width * height) as usize];
+        pattern.place(&mut cells, width, height, placement);
+        self.write_cells(&cells);
+    }
+
+    /// Maps a position in the target, in pixels from the top left, to the cell drawn there.
+    /// This is the inverse of the camera transform in `vs_main`. Positions off the board give
+    /// `None`.
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<(u32, u32)> {
+        let (width, height) = self.grid_size;
+        let (board_x, board_y) = self.camera.screen_to_board(x, y, self.size);
+        let in_board =
+            (0. ..width as f64).contains(&board_x) && (0. ..height as f64).contains(&board_y);
+        in_board.then_some((board_x as u32, board_y as u32))
+    }
+
+    /// Sets every cell on the straight line from `from` to `to` (both included) to `state`,
+    /// directly in the current generation. The ends may be off the board, only the part of
+    /// the line on it is painted.
+    pub fn paint_line(&mut self, from: (u32, u32), to: (u32, u32), state: u32) {
+        let Some((from, to)) = clip_line(from, to, self.grid_size) else {
+            return;
+        };
+        let (width, _) = self.grid_size;
+        let cells = self.current_cells();
+        for (x, y) in line_cells(from, to) {
+            let offset = (y * width + x) as u64 * 4;
+            self.queue
+                .write_buffer(cells, offset, bytemuck::bytes_of(&state));
+        }
+    }
+
+    /// Bitset counterpart of [`RenderContext::write_cells`], see
+    /// [`RenderContext::read_cells_bitset`] for the layout.
+    /// Panics if `bits` has fewer words than the board needs.
+    pub fn write_cells_bitset(&mut self, bits: &[u64]) {
+        let (width, height) = self.grid_size();
+        self.write_cells(&unpack_bitset(bits, (width * height) as usize));
+    }
+
+    pub fn step_rate(&self) -> StepRate {
+        self.step_rate
+    }
+
+    /// Changes how many generations each `render` computes.
+    pub fn set_step_rate(&mut self, step_rate: StepRate) {
+        self.step_rate = step_rate;
+        self.frame = 0;
+    }
+
+    /// Counts a frame and returns how many generations the step rate schedules in it.
+    pub fn advance_frame(&mut self) -> u64 {
+        let generations = self.step_rate.generations_in_frame(self.frame);
+        self.frame += 1;
+        generations as u64
+    }
+
+    /// Computes the generations due this frame according to the step rate (possibly none),
+    /// and draws the newest one to the target.
     pub fn render(&mut self) {
-        self.step = (self.step + 1) % 2;
-        dbg!(self.step);
-        let curr_surface_texture = self.surface.get_current_texture().unwrap();
-        let surface_texture_view = curr_surface_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
+        let generations = self.advance_frame();
+        self.render_generations(generations);
+    }
+
+    /// Computes exactly `generations` generations (possibly none) and draws the newest one to
+    /// the target, in one submission unless there are more than [`STEPS_PER_SUBMIT`]. For
+    /// callers running their own simulation clock.
+    pub fn render_generations(&mut self, generations: u64) {
+        let in_frame = generations.min(STEPS_PER_SUBMIT);
+        self.submit_steps(generations - in_frame);
+
+        let (curr_surface_texture, target_view) = match &self.target {
+            RenderTarget::Surface { surface, .. } => {
+                let surface_texture = surface.get_current_texture().unwrap();
+                let view = surface_texture
+                    .texture
+                    .create_view(&TextureViewDescriptor::default());
+                (Some(surface_texture), view)
+            }
+            RenderTarget::Texture(texture) => {
+                (None, texture.create_view(&TextureViewDescriptor::default()))
+            }
+        };
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("Encoder"),
             });
 
-        {
-            {
-                let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-                    label: Some("Compute Pass"),
-                    timestamp_writes: None,
+        self.encode_steps(&mut encoder, in_frame);
+        self.upload_shapes();
+        self.encode_draw(&mut encoder, &target_view);
+        self.queue.submit(iter::once(encoder.finish()));
+        if let Some(surface_texture) = curr_surface_texture {
+            surface_texture.present();
+        }
+    }
+
+    /// Computes `generations` generations without drawing anything, as fast as the GPU allows,
+    /// and blocks until they're done.
+    pub fn simulate(&mut self, generations: u64) {
+        self.submit_steps(generations);
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+
+    /// Submits `generations` generations, split into submissions of at most
+    /// [`STEPS_PER_SUBMIT`] so huge runs don't build one enormous command buffer.
+    fn submit_steps(&mut self, generations: u64) {
+        let mut remaining = generations;
+        while remaining > 0 {
+            let count = remaining.min(STEPS_PER_SUBMIT);
+            let mut encoder = self
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("Simulation Encoder"),
                 });
+            self.encode_steps(&mut encoder, count);
+            self.queue.submit(iter::once(encoder.finish()));
+            remaining -= count;
+        }
+    }
 
-                compute_pass.set_pipeline(&self.compute_pipeline);
-                compute_pass.set_bind_group(0, &self.bind_groups[(self.step) as usize], &[]);
-                const WORKGROUP_SIZE: u8 = 8;
-                let workgroup_count = (GRID_SIZE / WORKGROUP_SIZE as f32).ceil() as u32;
-                compute_pass.dispatch_workgroups(workgroup_count, workgroup_count, 1);
-            }
-            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("RenderPass"),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &surface_texture_view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: wgpu::LoadOp::Clear(Color {
-                            r: 10. / 255.,
-                            g: 12. / 255.,
-                            b: 28. / 255.,
-                            a: 1.,
-                        }),
-                        store: wgpu::StoreOp::Store,
+    /// Encodes `count` compute passes, flipping the ping-pong buffers after each one.
+    fn encode_steps(&mut self, encoder: &mut CommandEncoder, count: u64) {
+        for _ in 0..count {
+            self.step = (self.step + 1) % 2;
+            self.generation += 1;
+            self.encode_compute(encoder);
+        }
+    }
+
+    fn encode_compute(&self, encoder: &mut CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Compute Pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.bind_groups[(self.step) as usize], &[]);
+        const WORKGROUP_SIZE: u32 = 8;
+        let (width, height) = self.grid_size;
+        compute_pass.dispatch_workgroups(
+            width.div_ceil(WORKGROUP_SIZE),
+            height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+
+    fn encode_draw(&self, encoder: &mut CommandEncoder, target_view: &TextureView) {
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("RenderPass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                // with MSAA, draw into the multisampled texture and resolve into the target
+                view: self.msaa_view.as_ref().unwrap_or(target_view),
+                resolve_target: self.msaa_view.as_ref().map(|_| target_view),
+                ops: Operations {
+                    load: wgpu::LoadOp::Clear(self.background.to_wgpu(self.format)),
+                    // the multisampled texture is only needed until it's resolved
+                    store: match self.msaa_view {
+                        Some(_) => wgpu::StoreOp::Discard,
+                        None => wgpu::StoreOp::Store,
                     },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            pass.set_pipeline(&self.render_pipeline);
-            pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            pass.set_bind_group(0, &self.bind_groups[self.step as usize], &[]);
-            pass.draw_indexed(0..6, 0, 0..(GRID_SIZE * GRID_SIZE) as u32);
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.draw_cells(&mut pass);
+        self.draw_shapes(&mut pass);
+    }
+
+    fn draw_cells<'p>(&'p self, pass: &mut RenderPass<'p>) {
+        pass.set_pipeline(&self.render_pipeline);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        // draw the newest generation, which the bind group after `step` reads
+        pass.set_bind_group(0, &self.bind_groups[self.current_index()], &[]);
+        // cull cells outside the view: instance `i` is cell `i % width, i / width`, so visible
+        // rows are one contiguous range, or one range per row when columns are cut off too
+        let (width, _) = self.grid_size;
+        let Some(((x_min, x_max), (y_min, y_max))) =
+            self.camera.visible_cells(self.grid_size, self.size)
+        else {
+            return;
+        };
+        if x_min == 0 && x_max == width - 1 {
+            pass.draw_indexed(0..6, 0, y_min * width..(y_max + 1) * width);
+        } else {
+            for y in y_min..=y_max {
+                pass.draw_indexed(0..6, 0, y * width + x_min..y * width + x_max + 1);
+            }
         }
+    }
+
+    /// Draws the shapes uploaded by the last [`RenderContext::upload_shapes`] over the cells.
+    fn draw_shapes<'p>(&'p self, pass: &mut RenderPass<'p>) {
+        if self.shape_count == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.shape_pipeline);
+        pass.set_bind_group(0, &self.shape_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.shape_vertex_buffer.slice(..));
+        match self.antialiasing {
+            Antialiasing::Analytic => pass.draw(0..self.shape_count, 0..1),
+            Antialiasing::None | Antialiasing::Msaa4x => {
+                pass.set_index_buffer(self.shape_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..self.shape_count, 0, 0..1);
+            }
+        }
+    }
+
+    /// Shapes to draw over the board in the next frame. The list is emptied once it's drawn.
+    pub fn draw_list(&mut self) -> &mut DrawList {
+        &mut self.draw_list
+    }
+
+    /// Copies the draw list into the shape buffers, growing them if needed, and empties it.
+    fn upload_shapes(&mut self) {
+        if self.antialiasing == Antialiasing::Analytic {
+            // every corner needs its own distances to the outline, so nothing is shared
+            let vertices = self.draw_list.shape_vertices();
+            let vertex_bytes: &[u8] = bytemuck::cast_slice(&vertices);
+            grow_buffer(
+                &self.device,
+                &mut self.shape_vertex_buffer,
+                vertex_bytes.len(),
+            );
+            self.queue
+                .write_buffer(&self.shape_vertex_buffer, 0, vertex_bytes);
+            self.shape_count = vertices.len() as u32;
+        } else {
+            let vertex_bytes: &[u8] = bytemuck::cast_slice(self.draw_list.vertices());
+            let index_bytes: &[u8] = bytemuck::cast_slice(self.draw_list.indices());
+            grow_buffer(
+                &self.device,
+                &mut self.shape_vertex_buffer,
+                vertex_bytes.len(),
+            );
+            grow_buffer(
+                &self.device,
+                &mut self.shape_index_buffer,
+                index_bytes.len(),
+            );
+            self.queue
+                .write_buffer(&self.shape_vertex_buffer, 0, vertex_bytes);
+            self.queue
+                .write_buffer(&self.shape_index_buffer, 0, index_bytes);
+            self.shape_count = self.draw_list.indices().len() as u32;
+        }
+        self.draw_list.clear();
+    }
+
+    /// Draws the current generation and reads it back to the CPU as tightly packed RGBA8
+    /// pixels, without advancing the simulation.
+    ///
+    /// Headless contexts draw into their render target. Surface textures can't be read after
+    /// they're presented, so windowed contexts draw into a temporary texture instead.
+    ///
+    /// 8-bit RGBA and BGRA, `Rgb10a2Unorm` and `Rgba16Float` targets are converted to RGBA8,
+    /// other formats are an error.
+    pub fn read_frame(&self) -> Result<FrameImage, ReadFrameError> {
+        let bytes_per_pixel = match self.format {
+            TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Bgra8Unorm
+            | TextureFormat::Bgra8UnormSrgb
+            | TextureFormat::Rgb10a2Unorm => 4,
+            TextureFormat::Rgba16Float => 8,
+            format => return Err(ReadFrameError::UnsupportedFormat(format)),
+        };
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+
+        let scratch_texture;
+        let texture = match &self.target {
+            RenderTarget::Texture(texture) => texture,
+            RenderTarget::Surface { .. } => {
+                scratch_texture = create_target_texture(&self.device, self.format, self.size);
+                &scratch_texture
+            }
+        };
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        self.encode_draw(&mut encoder, &view);
+
+        let width = self.size.width;
+        let height = self.size.height;
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame readback buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
         self.queue.submit(iter::once(encoder.finish()));
-        curr_surface_texture.present();
+
+        let padded = read_buffer(&self.device, &readback_buffer);
+        let mut texels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        for row in padded.chunks_exact(padded_bytes_per_row as usize) {
+            texels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+
+        let pixels = match self.format {
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+                for pixel in texels.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+                texels
+            }
+            TextureFormat::Rgb10a2Unorm => texels
+                .chunks_exact(4)
+                .flat_map(|texel| {
+                    let packed = u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]);
+                    let channel = |shift: u32, max: u32| {
+                        (((packed >> shift) & max) as f32 / max as f32 * 255.).round() as u8
+                    };
+                    [
+                        channel(0, 0x3ff),
+                        channel(10, 0x3ff),
+                        channel(20, 0x3ff),
+                        channel(30, 0x3),
+                    ]
+                })
+                .collect(),
+            // the shaders already encoded the colours, as the format isn't sRGB
+            TextureFormat::Rgba16Float => texels
+                .chunks_exact(2)
+                .map(|half| {
+                    let value = f16_to_f32(u16::from_le_bytes([half[0], half[1]]));
+                    (value.clamp(0., 1.) * 255.).round() as u8
+                })
+                .collect(),
+            _ => texels,
+        };
+
+        Ok(FrameImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// A frame read back from the GPU: `width * height` RGBA8 pixels, row by row, without padding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl FrameImage {
+    /// Returns the RGBA value of the pixel at `(x, y)`, with the origin at the top left.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+}
+
+impl RenderContext<'static> {
+    /// Creates a context that renders into an offscreen texture of the given size, so the
+    /// simulation can run without a window. Falls back to a software adapter if no GPU is found.
+    pub fn new_headless(
+        width: u32,
+        height: u32,
+        grid_size: (u32, u32),
+        antialiasing: Antialiasing,
+    ) -> Result<RenderContext<'static>, HeadlessError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let adapter = pollster::block_on(request_headless_adapter(&instance))
+            .ok_or(HeadlessError::NoAdapter)?;
+        let (device, queue) = pollster::block_on(request_device(&adapter))?;
+
+        let size = PhysicalSize::new(width.max(1), height.max(1));
+        let texture = create_target_texture(&device, HEADLESS_FORMAT, size);
+        Ok(Self::with_target(
+            RenderTarget::Texture(texture),
+            device,
+            queue,
+            HEADLESS_FORMAT,
+            size,
+            grid_size,
+            antialiasing,
+        )?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadFrameError {
+    /// The render target's format can't be converted to RGBA8.
+    UnsupportedFormat(TextureFormat),
+}
+
+impl fmt::Display for ReadFrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadFrameError::UnsupportedFormat(format) => {
+                write!(f, "reading back {format:?} render targets is not supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReadFrameError {}
+
+/// A board with no cells, or too large for the device's storage buffers or for `u32` cell
+/// indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridSizeError {
+    pub grid_size: (u32, u32),
+    /// Most cells the device can hold.
+    pub max_cells: u64,
+}
+
+impl fmt::Display for GridSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = self.grid_size;
+        if width == 0 || height == 0 {
+            return write!(f, "a {width}x{height} grid has no cells");
+        }
+        write!(
+            f,
+            "a {width}x{height} grid has {} cells, but this device can hold at most {}",
+            width as u64 * height as u64,
+            self.max_cells
+        )
+    }
+}
+
+impl std::error::Error for GridSizeError {}
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    NoAdapter,
+    RequestDevice(RequestDeviceError),
+    GridSize(GridSizeError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::NoAdapter => write!(f, "no suitable adapter found"),
+            HeadlessError::RequestDevice(e) => write!(f, "setting up device failed: {e}"),
+            HeadlessError::GridSize(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<RequestDeviceError> for HeadlessError {
+    fn from(e: RequestDeviceError) -> Self {
+        HeadlessError::RequestDevice(e)
+    }
+}
+
+impl From<GridSizeError> for HeadlessError {
+    fn from(e: GridSizeError) -> Self {
+        HeadlessError::GridSize(e)
+    }
+}
+
+//utility functions
+
+/// Checks that a `width` x `height` board has cells, that each of its two cell buffers fits in
+/// the device's limits, and that every cell has a `u32` index.
+fn check_grid_size(device: &Device, (width, height): (u32, u32)) -> Result<(), GridSizeError> {
+    let limits = device.limits();
+    let max_bytes = (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+    let max_cells = (max_bytes / std::mem::size_of::<u32>() as u64).min(u32::MAX as u64);
+    let cells = width as u64 * height as u64;
+    if cells == 0 || cells > max_cells {
+        return Err(GridSizeError {
+            grid_size: (width, height),
+            max_cells,
+        });
+    }
+    Ok(())
+}
+
+/// Widens an IEEE 754 half precision float, as stored in `Rgba16Float` textures.
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1. } else { 1. };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0. => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1. + mantissa / 1024.) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Values for the `override` constants of the fragment shaders drawing into `format`.
+fn output_constants(format: TextureFormat) -> HashMap<String, f64> {
+    HashMap::from([(
+        "srgb_target".to_string(),
+        if format.is_srgb() { 1. } else { 0. },
+    )])
+}
+
+/// Pipeline for the draw list, alpha blended over the cells, and its camera bind group.
+fn create_shape_pipeline(
+    device: &Device,
+    format: TextureFormat,
+    antialiasing: Antialiasing,
+    camera_buffer: &Buffer,
+) -> (wgpu::RenderPipeline, BindGroup) {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shape Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shapes.wgsl").into()),
+    });
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("Shape Bind Group Layout"),
+        entries: &[BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Shape Bind Group"),
+        layout: &bind_group_layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Shape Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shape Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: match antialiasing {
+                Antialiasing::Analytic => "vs_main_analytic_aa",
+                Antialiasing::None | Antialiasing::Msaa4x => "vs_main",
+            },
+            buffers: &[match antialiasing {
+                Antialiasing::Analytic => ShapeVertex::LAYOUT,
+                Antialiasing::None | Antialiasing::Msaa4x => Vertex::LAYOUT,
+            }],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: match antialiasing {
+                Antialiasing::Analytic => "fs_main_analytic_aa",
+                Antialiasing::None | Antialiasing::Msaa4x => "fs_main",
+            },
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions {
+                constants: &output_constants(format),
+                ..Default::default()
+            },
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            // shapes come in either winding order
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: antialiasing.sample_count(),
+            ..Default::default()
+        },
+        multiview: None,
+    });
+    (pipeline, bind_group)
+}
+
+/// The first `count` cells of a bitset in the layout of [`RenderContext::read_cells_bitset`].
+fn unpack_bitset(bits: &[u64], count: usize) -> Vec<u32> {
+    assert!(
+        bits.len() >= count.div_ceil(64),
+        "ERROR: Bitset of {} words is too short for {count} cells.",
+        bits.len()
+    );
+    (0..count)
+        .map(|i| ((bits[i / 64] >> (i % 64)) & 1) as u32)
+        .collect()
+}
+
+/// Size new shape buffers start at, in bytes.
+const INITIAL_SHAPE_BUFFER_SIZE: u64 = 16 * 1024;
+
+fn create_shape_buffer(device: &Device, usage: wgpu::BufferUsages) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Shape buffer"),
+        size: INITIAL_SHAPE_BUFFER_SIZE,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Replaces `buffer` with a bigger one of the same usage if it can't hold `size` bytes.
+fn grow_buffer(device: &Device, buffer: &mut Buffer, size: usize) {
+    let size = size as u64;
+    if size <= buffer.size() {
+        return;
+    }
+    *buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Shape buffer"),
+        size: size.next_power_of_two(),
+        usage: buffer.usage(),
+        mapped_at_creation: false,
+    });
+}
+
+/// Shortens the line from `from` to `to` to the part on a `width` x `height` board
+/// (Liang-Barsky), or `None` if it misses the board. Lines within the board are unchanged.
+fn clip_line(
+    from: (u32, u32),
+    to: (u32, u32),
+    (width, height): (u32, u32),
+) -> Option<((u32, u32), (u32, u32))> {
+    let (x, y) = (from.0 as f64, from.1 as f64);
+    let (dx, dy) = (to.0 as f64 - x, to.1 as f64 - y);
+    let (mut enter, mut exit) = (0f64, 1f64);
+    // each side of the board as `p * t <= q`, for the point at `t` along the line
+    for (p, q) in [
+        (-dx, x),
+        (dx, width as f64 - 1. - x),
+        (-dy, y),
+        (dy, height as f64 - 1. - y),
+    ] {
+        if p == 0. {
+            if q < 0. {
+                return None;
+            }
+        } else if p < 0. {
+            enter = enter.max(q / p);
+        } else {
+            exit = exit.min(q / p);
+        }
+    }
+    if enter > exit {
+        return None;
+    }
+    let point = |t: f64| {
+        (
+            ((x + t * dx).round() as u32).min(width - 1),
+            ((y + t * dy).round() as u32).min(height - 1),
+        )
+    };
+    Some((point(enter), point(exit)))
+}
+
+/// Cells on the line between `from` and `to` (Bresenham's algorithm), so fast mouse drags
+/// still paint connected strokes.
+fn line_cells(from: (u32, u32), to: (u32, u32)) -> Vec<(u32, u32)> {
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (x1, y1) = (to.0 as i64, to.1 as i64);
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let step_x = if x < x1 { 1 } else { -1 };
+    let step_y = if y < y1 { 1 } else { -1 };
+    let mut error = dx + dy;
+    let mut cells = vec![];
+    loop {
+        cells.push((x as u32, y as u32));
+        if x == x1 && y == y1 {
+            return cells;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Contents of the `grid` uniform shared by all shaders.
+fn grid_uniform((width, height): (u32, u32)) -> [f32; 2] {
+    [width as f32, height as f32]
+}
+
+/// Creates the ping-pong pair of cell storage buffers, both initialised to `cells`, and the two
+/// bind groups that read one and write the other.
+fn create_cell_bindings(
+    device: &Device,
+    layout: &BindGroupLayout,
+    uniform_buffer: &Buffer,
+    rule_buffer: &Buffer,
+    boundary_buffer: &Buffer,
+    camera_buffer: &Buffer,
+    cells: &[u32],
+) -> ([Buffer; 2], [BindGroup; 2]) {
+    let cell_storage_buffers = [
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Cell storage buffer A"),
+            contents: bytemuck::cast_slice(cells),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        }),
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Cell storage buffer B"),
+            contents: bytemuck::cast_slice(cells),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        }),
+    ];
+
+    let bind_groups = [
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Uniform and Storage Bind Group A"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: cell_storage_buffers[0].as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: cell_storage_buffers[1].as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: rule_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: boundary_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+            ],
+        }),
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Uniform and Storage Bind Group B"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: cell_storage_buffers[1].as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: cell_storage_buffers[0].as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: rule_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: boundary_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+            ],
+        }),
+    ];
+
+    (cell_storage_buffers, bind_groups)
+}
+
+/// Blocks until `buffer` (created with `MAP_READ`) can be mapped and copies out its contents.
+fn read_buffer(device: &Device, buffer: &Buffer) -> Vec<u8> {
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| {
+        result.expect("ERROR: Mapping readback buffer failed.")
+    });
+    device.poll(wgpu::Maintain::Wait);
+    let data = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+    data
+}
+
+/// The multisampled texture MSAA draws into, or `None` without MSAA.
+fn create_msaa_view(
+    device: &Device,
+    format: TextureFormat,
+    size: PhysicalSize<u32>,
+    antialiasing: Antialiasing,
+) -> Option<TextureView> {
+    if antialiasing.sample_count() == 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Multisampled render target"),
+        size: Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: antialiasing.sample_count(),
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&TextureViewDescriptor::default()))
+}
+
+fn create_target_texture(
+    device: &Device,
+    format: TextureFormat,
+    size: PhysicalSize<u32>,
+) -> Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen render target"),
+        size: Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// Requests an adapter without a surface, falling back to a software adapter when there's no GPU.
+pub async fn request_headless_adapter(instance: &wgpu::Instance) -> Option<Adapter> {
+    let options = wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    };
+    if let Some(adapter) = instance.request_adapter(&options).await {
+        return Some(adapter);
+    }
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            force_fallback_adapter: true,
+            ..options
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_clipped_to_the_board() {
+        let board = (8, 6);
+        assert_eq!(clip_line((1, 2), (5, 3), board), Some(((1, 2), (5, 3))));
+        assert_eq!(
+            clip_line((0, 0), (u32::MAX, u32::MAX), board),
+            Some(((0, 0), (5, 5)))
+        );
+        assert_eq!(clip_line((20, 3), (30, 3), board), None);
+        assert_eq!(clip_line((3, 10), (3, 2), board), Some(((3, 5), (3, 2))));
+    }
+
+    #[test]
+    fn bitsets_unpack_to_one_state_per_cell() {
+        let cells = unpack_bitset(&[0b1011, 1], 65);
+        assert_eq!(&cells[..5], [1, 1, 0, 1, 0]);
+        assert_eq!(cells[64], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "too short for 65 cells")]
+    fn short_bitsets_are_rejected() {
+        unpack_bitset(&[u64::MAX], 65);
+    }
+
+    #[test]
+    fn empty_grid_is_an_error() {
+        for grid_size in [(0, 16), (16, 0)] {
+            match RenderContext::new_headless(16, 16, grid_size, Antialiasing::None) {
+                Err(HeadlessError::GridSize(e)) => assert_eq!(e.grid_size, grid_size),
+                Err(e) => return eprintln!("skipping GPU test: {e}"),
+                Ok(_) => panic!("expected a GridSizeError for {grid_size:?}"),
+            }
+        }
+        let Ok(mut ctx) = RenderContext::new_headless(16, 16, (4, 4), Antialiasing::None) else {
+            return;
+        };
+        assert!(ctx.resize_grid(0, 4).is_err());
+        assert_eq!(ctx.grid_size(), (4, 4));
+    }
+
+    #[test]
+    fn grid_larger_than_the_device_allows_is_an_error() {
+        match RenderContext::new_headless(16, 16, (6000, 6000), Antialiasing::None) {
+            Err(HeadlessError::GridSize(e)) => assert_eq!(e.grid_size, (6000, 6000)),
+            Err(e) => eprintln!("skipping GPU test: {e}"),
+            Ok(ctx) => {
+                let max_cells = ctx.device.limits().max_storage_buffer_binding_size / 4;
+                assert!(6000 * 6000 <= max_cells, "expected a GridSizeError");
+            }
+        }
     }
 }
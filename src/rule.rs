@@ -0,0 +1,283 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// An outer-totalistic life-like rule: whether a cell is alive in the next generation depends
+/// only on whether it's alive now and how many of its 8 neighbours are.
+///
+/// Bit `n` of `birth` is set when a dead cell with `n` live neighbours comes alive, bit `n` of
+/// `survive` when a live cell with `n` live neighbours stays alive. This is also the layout of
+/// the `Rule` uniform in `compute.wgsl`.
+///
+/// With more than 2 `states` this is a "Generations" rule: state 0 is dead, 1 is alive, and a
+/// live cell that doesn't survive goes through the refractory states `2..states` one generation
+/// at a time before it's dead again. Only state 1 counts as a live neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    pub birth: u16,
+    pub survive: u16,
+    pub states: u32,
+}
+
+/// Most states a Generations rule can have, as in Golly. RLE files can't encode many more.
+pub const MAX_STATES: u32 = 256;
+
+impl Rule {
+    /// Conway's Game of Life, B3/S23.
+    pub const CONWAY: Rule = Rule::from_counts(&[3], &[2, 3]);
+    /// HighLife, B36/S23.
+    pub const HIGH_LIFE: Rule = Rule::from_counts(&[3, 6], &[2, 3]);
+    /// Seeds, B2/S.
+    pub const SEEDS: Rule = Rule::from_counts(&[2], &[]);
+    /// Day & Night, B3678/S34678.
+    pub const DAY_AND_NIGHT: Rule = Rule::from_counts(&[3, 6, 7, 8], &[3, 4, 6, 7, 8]);
+    /// Brian's Brain, B2/S/C3.
+    pub const BRIANS_BRAIN: Rule = Rule::from_counts(&[2], &[]).with_states(3);
+    /// Star Wars, B2/S345/C4.
+    pub const STAR_WARS: Rule = Rule::from_counts(&[2], &[3, 4, 5]).with_states(4);
+
+    /// Builds a two state rule from the neighbour counts that cause birth and survival.
+    pub const fn from_counts(birth: &[u8], survive: &[u8]) -> Rule {
+        Rule {
+            birth: mask(birth),
+            survive: mask(survive),
+            states: 2,
+        }
+    }
+
+    /// Turns the rule into a Generations rule with `states` states in total. Panics unless
+    /// that's between 2 and [`MAX_STATES`].
+    pub const fn with_states(self, states: u32) -> Rule {
+        assert!(
+            states >= 2 && states <= MAX_STATES,
+            "ERROR: Generations rules have between 2 and MAX_STATES states."
+        );
+        Rule { states, ..self }
+    }
+
+    pub fn births_on(&self, neighbors: u32) -> bool {
+        (self.birth >> neighbors) & 1 == 1
+    }
+
+    pub fn survives_on(&self, neighbors: u32) -> bool {
+        (self.survive >> neighbors) & 1 == 1
+    }
+
+    /// State of a cell in the next generation, given its current state and live neighbours.
+    pub fn next_state(&self, state: u32, neighbors: u32) -> u32 {
+        match state {
+            0 => self.births_on(neighbors) as u32,
+            1 if self.survives_on(neighbors) => 1,
+            // live cells that die and refractory cells move one state closer to dead
+            _ => (state + 1) % self.states,
+        }
+    }
+
+    /// Contents of the `Rule` uniform.
+    pub fn to_uniform(&self) -> [u32; 3] {
+        [self.birth as u32, self.survive as u32, self.states]
+    }
+}
+
+const fn mask(counts: &[u8]) -> u16 {
+    let mut mask = 0;
+    let mut i = 0;
+    while i < counts.len() {
+        mask |= 1 << counts[i];
+        i += 1;
+    }
+    mask
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::CONWAY
+    }
+}
+
+/// Formats the rule in B/S notation, e.g. `B36/S23`, or `B2/S/C3` for Generations rules.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |mask: u16| -> String {
+            (0..=8)
+                .filter(|n| (mask >> n) & 1 == 1)
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
+        };
+        write!(f, "B{}/S{}", digits(self.birth), digits(self.survive))?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRuleError {
+    /// The rule isn't two `/`-separated parts.
+    MissingSeparator,
+    /// A neighbour count outside `0..=8`, or some other unexpected character.
+    InvalidCharacter(char),
+    /// Both parts were prefixed with the same letter, like `B3/B23`.
+    DuplicatePart(char),
+    /// The Generations state count isn't a number from 2 to [`MAX_STATES`].
+    InvalidStates(String),
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRuleError::MissingSeparator => {
+                write!(
+                    f,
+                    "expected a rule like B3/S23 with birth and survival separated by '/'"
+                )
+            }
+            ParseRuleError::InvalidCharacter(c) => write!(f, "unexpected character {c:?} in rule"),
+            ParseRuleError::DuplicatePart(c) => write!(f, "rule has two {c:?} parts"),
+            ParseRuleError::InvalidStates(states) => {
+                write!(
+                    f,
+                    "invalid number of states {states:?}, expected 2 to {MAX_STATES}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
+
+/// Parses `B3/S23` style rules (case insensitive, either part first) and the older `23/3`
+/// survival/birth notation. Generations rules add the number of states as a third part, as in
+/// `B2/S/C3` or `/2/3`.
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, rest) = s
+            .trim()
+            .split_once('/')
+            .ok_or(ParseRuleError::MissingSeparator)?;
+        let (second, states) = match rest.split_once('/') {
+            Some((second, states)) => (second, Some(parse_states(states)?)),
+            None => (rest, None),
+        };
+
+        let mut birth = None;
+        let mut survive = None;
+        for (i, part) in [first, second].into_iter().enumerate() {
+            let part = part.trim();
+            let (letter, counts) = match part.chars().next().map(|c| c.to_ascii_uppercase()) {
+                Some(letter @ ('B' | 'S')) => (letter, &part[1..]),
+                // no letters means S/B notation
+                _ if i == 0 => ('S', part),
+                _ => ('B', part),
+            };
+            let slot = if letter == 'B' {
+                &mut birth
+            } else {
+                &mut survive
+            };
+            if slot.is_some() {
+                return Err(ParseRuleError::DuplicatePart(letter));
+            }
+            *slot = Some(parse_counts(counts)?);
+        }
+
+        Ok(Rule {
+            birth: birth.unwrap_or(0),
+            survive: survive.unwrap_or(0),
+            states: states.unwrap_or(2),
+        })
+    }
+}
+
+fn parse_counts(counts: &str) -> Result<u16, ParseRuleError> {
+    counts
+        .chars()
+        .try_fold(0u16, |mask, c| match c.to_digit(10) {
+            Some(n) if n <= 8 => Ok(mask | 1 << n),
+            _ => Err(ParseRuleError::InvalidCharacter(c)),
+        })
+}
+
+fn parse_states(states: &str) -> Result<u32, ParseRuleError> {
+    let states = states.trim();
+    let count = match states.chars().next().map(|c| c.to_ascii_uppercase()) {
+        Some('C' | 'G') => &states[1..],
+        _ => states,
+    };
+    match count.parse() {
+        Ok(count) if (2..=MAX_STATES).contains(&count) => Ok(count),
+        _ => Err(ParseRuleError::InvalidStates(states.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_notation() {
+        let life = Rule::CONWAY;
+        for (source, rule) in [
+            ("B3/S23", life),
+            ("S23/B3", life),
+            ("b3/s23", life),
+            ("s23/B3", life),
+            (" B36/S23 ", Rule::HIGH_LIFE),
+            ("B2/S", Rule::SEEDS),
+            // older S/B notation
+            ("23/3", life),
+            ("34678/3678", Rule::DAY_AND_NIGHT),
+            // Generations
+            ("/2/3", Rule::BRIANS_BRAIN),
+            ("B2/S/C3", Rule::BRIANS_BRAIN),
+            ("B2/S345/G4", Rule::STAR_WARS),
+            ("345/2/4", Rule::STAR_WARS),
+        ] {
+            assert_eq!(source.parse(), Ok(rule), "{source}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        for (source, error) in [
+            ("B3S23", ParseRuleError::MissingSeparator),
+            ("B9/S", ParseRuleError::InvalidCharacter('9')),
+            ("B3/Sx", ParseRuleError::InvalidCharacter('x')),
+            ("B3/B23", ParseRuleError::DuplicatePart('B')),
+            ("S3/S23", ParseRuleError::DuplicatePart('S')),
+            ("B2/S/C1", ParseRuleError::InvalidStates("C1".to_string())),
+            ("B2/S/Cx", ParseRuleError::InvalidStates("Cx".to_string())),
+        ] {
+            assert_eq!(source.parse::<Rule>(), Err(error), "{source}");
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for rule in [
+            Rule::CONWAY,
+            Rule::HIGH_LIFE,
+            Rule::SEEDS,
+            Rule::DAY_AND_NIGHT,
+            Rule::BRIANS_BRAIN,
+            Rule::STAR_WARS,
+            Rule::from_counts(&[0, 8], &[0, 1, 2, 3, 4, 5, 6, 7, 8]).with_states(MAX_STATES),
+        ] {
+            assert_eq!(rule.to_string().parse(), Ok(rule), "{rule}");
+        }
+        assert_eq!(Rule::HIGH_LIFE.to_string(), "B36/S23");
+        assert_eq!(Rule::STAR_WARS.to_string(), "B2/S345/C4");
+    }
+
+    #[test]
+    fn generations_rules_have_at_most_max_states() {
+        let rule: Rule = "B2/S/C256".parse().unwrap();
+        assert_eq!(rule.states, MAX_STATES);
+        assert_eq!(
+            "B2/S/C1000".parse::<Rule>(),
+            Err(ParseRuleError::InvalidStates("C1000".to_string()))
+        );
+    }
+}
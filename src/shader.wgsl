@@ -2,38 +2,93 @@
 
 struct VertexInput {
     @location(0) pos: vec2f,
+    @location(1) color: vec4f,
     @builtin(instance_index) instance: u32,
 };
 
 @group(0) @binding(0) var<uniform> grid: vec2f; 
 @group(0) @binding(1) var<storage> cell_state: array<u32>;
 
+struct Rule {
+    birth: u32,
+    survive: u32,
+    states: u32,
+}
+@group(0) @binding(3) var<uniform> rule: Rule;
+
+struct Camera {
+    // board point at the centre of the screen
+    center: vec2f,
+    // clip space units per cell along x and y, so cells stay square
+    scale: vec2f,
+}
+@group(0) @binding(5) var<uniform> camera: Camera;
+
+// false when the target stores what it's given rather than encoding to sRGB itself, so the
+// linear colours have to be encoded here. Set per pipeline from the target format
+override srgb_target: bool = true;
+
+fn encode_output(color: vec4f) -> vec4f {
+    if srgb_target {
+        return color;
+    }
+    let c = color.rgb;
+    let encoded = select(1.055 * pow(c, vec3f(1. / 2.4)) - 0.055, c * 12.92, c <= vec3f(0.0031308));
+    return vec4f(encoded, color.a);
+}
+
 struct VertexOutput {
     @builtin(position) pos: vec4f,
+    // how far along its refractory states a dying cell is, 0 for live cells
+    @location(0) @interpolate(flat) fade: f32,
+    @location(1) color: vec4f,
+    // position inside the cell's quad, which spans -0.8..0.8
+    @location(2) local: vec2f,
 }
 
 @vertex
 fn vs_main(input: VertexInput) -> VertexOutput {
-    let i = f32(input.instance);
     let pos = input.pos;
-    let cell = vec2f(i % grid.x, floor(i / grid.y)) ;
-    let cell_offset = cell / grid * 2;
+    // in integers, as f32 can't tell instances apart past 2^24 cells
+    let width = u32(grid.x);
+    let cell = vec2f(f32(input.instance % width), f32(input.instance / width));
 
-    let state = f32(cell_state[input.instance]);
+    let state = cell_state[input.instance];
     // setting position of all vertices of an instance to 0 = scaling the instance to 0
-    let grid_pos = ((pos + 1) / grid) -1 + cell_offset;
+    let visible = select(1., 0., state == 0u);
+    // the quad spans -1..1, so this puts it inside the cell in board coordinates
+    let board_pos = cell + (pos + 1) / 2;
+    let clip_pos = (board_pos - camera.center) * camera.scale;
 
     var output: VertexOutput;
-    output.pos = vec4f(grid_pos * state, 0, 1);
+    output.pos = vec4f(clip_pos * visible, 0, 1);
+    output.fade = f32(max(state, 1u) - 1u) / f32(max(rule.states - 1u, 1u));
+    output.color = input.color;
+    output.local = pos;
     return  output;
 }
 
 
 
+fn cell_color(input: VertexOutput) -> vec4f {
+    // refractory states of Generations rules fade from the live color towards the background
+    let dying = vec3f(0.2, 0.1, 0.35);
+    return vec4f(mix(input.color.rgb, dying, input.fade), input.color.a);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4f {
+    return encode_output(cell_color(input));
+}
+
+// fs_main with the quad's edges smoothed by their signed distance, measured in pixels
 @fragment
-fn fs_main() -> @location(0) vec4f {
-    let color = vec4f(0.8, 0.8, 0.5, 1) ;
-    return color;
+fn fs_main_analytic_aa(input: VertexOutput) -> @location(0) vec4f {
+    let color = cell_color(input);
+    let distance = max(abs(input.local.x), abs(input.local.y)) - 0.8;
+    let pixel = max(fwidth(input.local.x), fwidth(input.local.y));
+    let coverage = clamp(0.5 - distance / pixel, 0., 1.);
+    return encode_output(vec4f(color.rgb, color.a * coverage));
 }
 
 
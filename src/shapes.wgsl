@@ -0,0 +1,82 @@
+struct Camera {
+    // board point at the centre of the screen
+    center: vec2f,
+    // clip space units per cell along x and y
+    scale: vec2f,
+}
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+// false when the target stores what it's given rather than encoding to sRGB itself, so the
+// linear colours have to be encoded here. Set per pipeline from the target format
+override srgb_target: bool = true;
+
+fn encode_output(color: vec4f) -> vec4f {
+    if srgb_target {
+        return color;
+    }
+    let c = color.rgb;
+    let encoded = select(1.055 * pow(c, vec3f(1. / 2.4)) - 0.055, c * 12.92, c <= vec3f(0.0031308));
+    return vec4f(encoded, color.a);
+}
+
+struct VertexInput {
+    // in board coordinates, like the cells
+    @location(0) pos: vec2f,
+    @location(1) color: vec4f,
+};
+
+// vertices of unindexed triangles, for analytic antialiasing
+struct AnalyticVertexInput {
+    @location(0) pos: vec2f,
+    @location(1) color: vec4f,
+    // barycentric distance to each outline edge of the triangle, 1 throughout for the others
+    @location(2) edges: vec3f,
+};
+
+struct VertexOutput {
+    @builtin(position) pos: vec4f,
+    @location(0) color: vec4f,
+    @location(1) edges: vec3f,
+}
+
+fn to_clip(pos: vec2f) -> vec4f {
+    return vec4f((pos - camera.center) * camera.scale, 0, 1);
+}
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.pos = to_clip(input.pos);
+    output.color = input.color;
+    // without outline distances nothing fades
+    output.edges = vec3f(1.);
+    return output;
+}
+
+@vertex
+fn vs_main_analytic_aa(input: AnalyticVertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.pos = to_clip(input.pos);
+    output.color = input.color;
+    output.edges = input.edges;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4f {
+    return encode_output(input.color);
+}
+
+// fs_main with the outline edges smoothed by their distance, measured in pixels
+@fragment
+fn fs_main_analytic_aa(input: VertexOutput) -> @location(0) vec4f {
+    // the barycentric distances change linearly, so dividing by their gradient gives pixels.
+    // Edges inside the shape have no gradient and stay far away
+    let dx = dpdx(input.edges);
+    let dy = dpdy(input.edges);
+    let gradient = sqrt(dx * dx + dy * dy);
+    let pixels = input.edges / max(gradient, vec3f(1e-6));
+    let distance = min(pixels.x, min(pixels.y, pixels.z));
+    let coverage = clamp(0.5 + distance, 0., 1.);
+    return encode_output(vec4f(input.color.rgb, input.color.a * coverage));
+}
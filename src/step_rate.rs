@@ -0,0 +1,176 @@
+use std::fmt;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+
+/// How fast the simulation runs relative to the frame rate: `generations` generations every
+/// `frames` frames, e.g. 4 per frame, or 1 every 3 frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StepRate {
+    pub generations: u32,
+    pub frames: NonZeroU32,
+}
+
+/// Fastest rate, so that a frame's generations stay a reasonable amount of GPU work.
+pub const MAX_GENERATIONS_PER_FRAME: u32 = 1024;
+
+impl StepRate {
+    /// One generation per frame.
+    pub const ONE: StepRate = StepRate::per_frame(1);
+
+    pub const fn per_frame(generations: u32) -> StepRate {
+        StepRate {
+            generations,
+            frames: NonZeroU32::MIN,
+        }
+    }
+
+    /// One generation every `frames` frames.
+    pub const fn every(frames: NonZeroU32) -> StepRate {
+        StepRate {
+            generations: 1,
+            frames,
+        }
+    }
+
+    /// How many generations to compute in frame number `frame`. Spreads the generations as
+    /// evenly as possible, so over any `frames` consecutive frames exactly `generations` run.
+    pub fn generations_in_frame(&self, frame: u64) -> u32 {
+        let generations = self.generations as u64;
+        let frames = self.frames.get() as u64;
+        let phase = frame % frames;
+        ((phase + 1) * generations / frames - phase * generations / frames) as u32
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.generations as f64 / self.frames.get() as f64
+    }
+
+    /// Most generations computed in any one frame.
+    pub fn max_in_frame(&self) -> u32 {
+        self.generations.div_ceil(self.frames.get())
+    }
+
+    /// Twice as fast: half as many frames per step, or twice the generations once that's one.
+    /// Stays the same once that would pass [`MAX_GENERATIONS_PER_FRAME`].
+    pub fn doubled(&self) -> StepRate {
+        let doubled = match NonZeroU32::new(self.frames.get() / 2) {
+            Some(frames) if self.frames.get().is_multiple_of(2) => StepRate { frames, ..*self },
+            _ => StepRate {
+                generations: self.generations.saturating_mul(2).max(1),
+                ..*self
+            },
+        };
+        if doubled.max_in_frame() > MAX_GENERATIONS_PER_FRAME {
+            return *self;
+        }
+        doubled
+    }
+
+    /// Half as fast: half the generations, or twice the frames per step once that's one.
+    pub fn halved(&self) -> StepRate {
+        if self.generations > 1 && self.generations.is_multiple_of(2) {
+            StepRate {
+                generations: self.generations / 2,
+                ..*self
+            }
+        } else {
+            StepRate {
+                frames: self.frames.saturating_mul(NonZeroU32::new(2).unwrap()),
+                ..*self
+            }
+        }
+    }
+}
+
+impl Default for StepRate {
+    fn default() -> Self {
+        StepRate::ONE
+    }
+}
+
+/// Formats the rate as `N`, or `N/K` when it isn't a whole number per frame.
+impl fmt::Display for StepRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.frames.get() == 1 {
+            write!(f, "{}", self.generations)
+        } else {
+            write!(f, "{}/{}", self.generations, self.frames)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseStepRateError(pub String);
+
+impl fmt::Display for ParseStepRateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid rate {:?}, expected generations per frame like 4, or a fraction like 1/3, \
+             up to {MAX_GENERATIONS_PER_FRAME} per frame",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseStepRateError {}
+
+/// Parses `N` generations per frame, or `N/K` for `N` generations every `K` frames.
+impl FromStr for StepRate {
+    type Err = ParseStepRateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseStepRateError(s.to_string());
+        let (generations, frames) = match s.trim().split_once('/') {
+            Some((generations, frames)) => (generations, frames.trim()),
+            None => (s.trim(), "1"),
+        };
+        let rate = StepRate {
+            generations: generations.trim().parse().map_err(|_| error())?,
+            frames: frames.parse().map_err(|_| error())?,
+        };
+        if rate.max_in_frame() > MAX_GENERATIONS_PER_FRAME {
+            return Err(error());
+        }
+        Ok(rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreads_generations_evenly_over_frames() {
+        let rate: StepRate = "3/4".parse().unwrap();
+        let per_frame: Vec<u32> = (0..8)
+            .map(|frame| rate.generations_in_frame(frame))
+            .collect();
+        assert_eq!(per_frame, [0, 1, 1, 1, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn doubling_and_halving_pass_through_one_per_frame() {
+        let mut rate: StepRate = "1/4".parse().unwrap();
+        let mut seen = vec![rate.to_string()];
+        for _ in 0..4 {
+            rate = rate.doubled();
+            seen.push(rate.to_string());
+        }
+        assert_eq!(seen, ["1/4", "1/2", "1", "2", "4"]);
+        for _ in 0..4 {
+            rate = rate.halved();
+        }
+        assert_eq!(rate.to_string(), "1/4");
+    }
+
+    #[test]
+    fn rates_stop_at_the_maximum_per_frame() {
+        let max = StepRate::per_frame(MAX_GENERATIONS_PER_FRAME);
+        assert_eq!(max.to_string().parse(), Ok(max));
+        assert_eq!(max.doubled(), max);
+        assert!("4000000000".parse::<StepRate>().is_err());
+        assert!("2049/2".parse::<StepRate>().is_err());
+        assert!("2048/2".parse::<StepRate>().is_ok());
+    }
+}
@@ -0,0 +1,579 @@
+//! Turns paths into indexed triangle lists on the CPU, ready for a vertex and index buffer
+//! like [`CELL_VERTICES`] and [`INDICES`].
+//!
+//! Strokes are built from one quad per segment plus separate join and cap triangles, so
+//! triangles overlap at the corners. That's invisible with opaque colours, but translucent
+//! strokes come out darker where they overlap.
+//!
+//! [`CELL_VERTICES`]: crate::primitives::CELL_VERTICES
+//! [`INDICES`]: crate::primitives::INDICES
+
+use std::f32::consts::PI;
+
+use crate::primitives::Point2;
+
+/// Round joins and caps use one triangle per this fraction of a half turn.
+const ROUND_SEGMENTS_PER_HALF_TURN: f32 = 16.;
+
+/// Segments shorter than this are dropped, as they have no direction.
+const EPSILON: f32 = 1e-6;
+
+/// How the outside corner between two segments of a stroke is filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    /// Extends both edges until they meet, falling back to a bevel past the miter limit.
+    #[default]
+    Miter,
+    /// An arc around the corner point.
+    Round,
+    /// A straight edge cutting the corner off.
+    Bevel,
+}
+
+/// How the two ends of an open stroke are finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// Ends flush with the end points.
+    #[default]
+    Butt,
+    /// Extends half the width past the end points.
+    Square,
+    /// A half circle around the end points.
+    Round,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// Longest a miter may be, as a multiple of the width, before it's beveled instead. The
+    /// default of 4 (as in SVG) bevels corners sharper than about 29 degrees.
+    pub miter_limit: f32,
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> StrokeStyle {
+        StrokeStyle {
+            width,
+            ..StrokeStyle::default()
+        }
+    }
+
+    pub fn with_join(self, join: LineJoin) -> StrokeStyle {
+        StrokeStyle { join, ..self }
+    }
+
+    pub fn with_cap(self, cap: LineCap) -> StrokeStyle {
+        StrokeStyle { cap, ..self }
+    }
+
+    pub fn with_miter_limit(self, miter_limit: f32) -> StrokeStyle {
+        StrokeStyle {
+            miter_limit,
+            ..self
+        }
+    }
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            width: 1.,
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            miter_limit: 4.,
+        }
+    }
+}
+
+/// Triangles as vertex positions and three indices per triangle.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Point2>,
+    pub indices: Vec<u32>,
+    /// One mask per triangle, with bit `k` set when its edge from corner `k` to the next
+    /// corner is on the outline of the shape rather than inside it.
+    pub outline: Vec<u8>,
+}
+
+impl Mesh {
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Summed area of all triangles. Overlapping triangles are counted more than once.
+    pub fn area(&self) -> f32 {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize]);
+                ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)).abs() / 2.
+            })
+            .sum()
+    }
+
+    /// The indices as `u16`, for meshes small enough to use `IndexFormat::Uint16`.
+    pub fn indices_u16(&self) -> Option<Vec<u16>> {
+        if self.vertices.len() > u16::MAX as usize + 1 {
+            return None;
+        }
+        Some(self.indices.iter().map(|&i| i as u16).collect())
+    }
+
+    /// Appends a triangle fan around `points[0]`. `outline(k)` tells whether the polygon
+    /// edge from `points[k]` to the next point is on the outline; the fan's inner diagonals
+    /// never are.
+    fn push_fan(&mut self, points: &[Point2], outline: impl Fn(usize) -> bool) {
+        let base = self.vertices.len() as u32;
+        let n = points.len();
+        self.vertices.extend_from_slice(points);
+        for i in 1..n - 1 {
+            self.indices
+                .extend([base, base + i as u32, base + i as u32 + 1]);
+            let edges = [
+                i == 1 && outline(0),
+                outline(i),
+                i == n - 2 && outline(n - 1),
+            ];
+            self.outline.push(outline_mask(edges));
+        }
+    }
+
+    /// Appends the triangles of an arc of radius `radius` around `center`, from angle `start`
+    /// turning by `sweep` radians (counter-clockwise when positive).
+    fn push_arc(&mut self, center: Point2, radius: f32, start: f32, sweep: f32) {
+        let segments = (sweep.abs() / PI * ROUND_SEGMENTS_PER_HALF_TURN)
+            .ceil()
+            .max(1.) as u32;
+        let mut fan = vec![center];
+        fan.extend((0..=segments).map(|i| {
+            let angle = start + sweep * i as f32 / segments as f32;
+            Point2::new(
+                center.x + radius * angle.cos(),
+                center.y + radius * angle.sin(),
+            )
+        }));
+        self.push_fan(&fan, rim(fan.len()));
+    }
+}
+
+/// Packs whether each edge of a triangle is on the outline into a [`Mesh::outline`] mask.
+fn outline_mask(edges: [bool; 3]) -> u8 {
+    edges
+        .iter()
+        .enumerate()
+        .fold(0, |mask, (k, &edge)| mask | (edge as u8) << k)
+}
+
+/// Outline of an `n` point fan around a corner: the rim, not the two edges from the centre.
+fn rim(n: usize) -> impl Fn(usize) -> bool {
+    move |k| k != 0 && k != n - 1
+}
+
+/// Fills a simple polygon (edges may not cross), convex or not, in either winding order.
+pub fn fill(points: &[Point2]) -> Mesh {
+    if points.len() < 3 {
+        return Mesh::default();
+    }
+    let indices = triangulate(points);
+    // the polygon's own edges join neighbouring points, the ones clipping added don't
+    let n = points.len() as u32;
+    let is_edge = |a: u32, b: u32| a.abs_diff(b) == 1 || a.abs_diff(b) == n - 1;
+    let outline = indices
+        .chunks_exact(3)
+        .map(|t| {
+            outline_mask([
+                is_edge(t[0], t[1]),
+                is_edge(t[1], t[2]),
+                is_edge(t[2], t[0]),
+            ])
+        })
+        .collect();
+    Mesh {
+        vertices: points.to_vec(),
+        indices,
+        outline,
+    }
+}
+
+/// Outlines the polyline through `points` with `style`. A `closed` path also joins the last
+/// point back to the first, and has no caps.
+pub fn stroke(points: &[Point2], closed: bool, style: &StrokeStyle) -> Mesh {
+    let mut mesh = Mesh::default();
+    let half_width = style.width / 2.;
+
+    let mut points: Vec<Point2> = points.to_vec();
+    points.dedup_by(|a, b| a.distance(*b) < EPSILON);
+    if closed && points.len() > 1 && points[0].distance(points[points.len() - 1]) < EPSILON {
+        points.pop();
+    }
+    if points.len() < 2 || half_width <= 0. {
+        return mesh;
+    }
+    let closed = closed && points.len() > 2;
+
+    let segment_count = if closed {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    let directions: Vec<Point2> = (0..segment_count)
+        .map(|i| direction(points[i], points[(i + 1) % points.len()]))
+        .collect();
+
+    for (i, &dir) in directions.iter().enumerate() {
+        let (from, to) = (points[i], points[(i + 1) % points.len()]);
+        let offset = scale(normal(dir), half_width);
+        // the ends are only on the outline where a butt cap leaves them bare
+        let butt = !closed && style.cap == LineCap::Butt;
+        let (first, last) = (i == 0, i == segment_count - 1);
+        mesh.push_fan(
+            &[
+                add(from, offset),
+                sub(from, offset),
+                sub(to, offset),
+                add(to, offset),
+            ],
+            |k| match k {
+                0 => butt && first,
+                2 => butt && last,
+                _ => true,
+            },
+        );
+    }
+
+    // joins at every corner: all points of a closed path, the inner ones of an open path
+    let corners = if closed {
+        0..points.len()
+    } else {
+        1..points.len() - 1
+    };
+    for i in corners {
+        let incoming = directions[(i + segment_count - 1) % segment_count];
+        let outgoing = directions[i % segment_count];
+        push_join(&mut mesh, points[i], incoming, outgoing, half_width, style);
+    }
+
+    if !closed {
+        push_cap(
+            &mut mesh,
+            points[0],
+            scale(directions[0], -1.),
+            half_width,
+            style.cap,
+        );
+        let last = points[points.len() - 1];
+        push_cap(
+            &mut mesh,
+            last,
+            directions[segment_count - 1],
+            half_width,
+            style.cap,
+        );
+    }
+    mesh
+}
+
+/// Fills the gap on the outside of the corner at `point`.
+fn push_join(
+    mesh: &mut Mesh,
+    point: Point2,
+    incoming: Point2,
+    outgoing: Point2,
+    half_width: f32,
+    style: &StrokeStyle,
+) {
+    let turn = cross(incoming, outgoing);
+    let straight = dot(incoming, outgoing);
+    if turn.abs() < EPSILON && straight > 0. {
+        return;
+    }
+    // the outside of a left turn is on the right, and the other way round. A full U-turn
+    // has no outside, so just pick the left
+    let side = if turn.abs() < EPSILON {
+        1.
+    } else {
+        -turn.signum()
+    };
+    let from = scale(normal(incoming), side * half_width);
+    let to = scale(normal(outgoing), side * half_width);
+
+    match style.join {
+        LineJoin::Round => {
+            let start = from.y.atan2(from.x);
+            // the offsets turn with the path, so the arc turns the same way, the short way
+            // round. A U-turn goes round the front of the corner
+            let sweep = if turn.abs() < EPSILON {
+                -side * PI
+            } else {
+                let sweep = to.y.atan2(to.x) - start;
+                (sweep + PI).rem_euclid(2. * PI) - PI
+            };
+            mesh.push_arc(point, half_width, start, sweep);
+        }
+        LineJoin::Miter | LineJoin::Bevel => {
+            let bisector = add(from, to);
+            let bisector_length = bisector.x.hypot(bisector.y);
+            // the miter tip is half_width / cos(angle / 2) from the corner
+            let cos_half_angle = bisector_length / (2. * half_width);
+            let miter_ratio = 1. / cos_half_angle.max(EPSILON);
+            if style.join == LineJoin::Miter && miter_ratio <= style.miter_limit {
+                let tip = scale(bisector, half_width * miter_ratio / bisector_length);
+                mesh.push_fan(
+                    &[point, add(point, from), add(point, tip), add(point, to)],
+                    rim(4),
+                );
+            } else {
+                mesh.push_fan(&[point, add(point, from), add(point, to)], rim(3));
+            }
+        }
+    }
+}
+
+/// Finishes an open end at `point`, with `outward` pointing away from the stroke.
+fn push_cap(mesh: &mut Mesh, point: Point2, outward: Point2, half_width: f32, cap: LineCap) {
+    let offset = scale(normal(outward), half_width);
+    match cap {
+        LineCap::Butt => {}
+        LineCap::Square => {
+            let extension = scale(outward, half_width);
+            // the edge across `point` is shared with the segment
+            mesh.push_fan(
+                &[
+                    add(point, offset),
+                    sub(point, offset),
+                    add(sub(point, offset), extension),
+                    add(add(point, offset), extension),
+                ],
+                |k| k != 0,
+            );
+        }
+        LineCap::Round => {
+            // from the left of `outward`, around its tip to the right
+            let start = offset.y.atan2(offset.x);
+            mesh.push_arc(point, half_width, start, -PI);
+        }
+    }
+}
+
+/// Splits a simple polygon into triangles by ear clipping, returning indices into `points`.
+fn triangulate(points: &[Point2]) -> Vec<u32> {
+    // twice the signed area, positive for counter-clockwise polygons
+    let area: f32 = (0..points.len())
+        .map(|i| cross(points[i], points[(i + 1) % points.len()]))
+        .sum();
+    let orientation = area.signum();
+    let turn = |a: Point2, b: Point2, c: Point2| cross(sub(b, a), sub(c, a)) * orientation;
+
+    let mut remaining: Vec<u32> = (0..points.len() as u32).collect();
+    let mut triangles = Vec::with_capacity((points.len() - 2) * 3);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let corner = |i: usize| {
+            [
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            ]
+        };
+        let ear = (0..n).find(|&i| {
+            let [a, b, c] = corner(i).map(|index| points[index as usize]);
+            // an ear is a convex corner with no other corner inside its triangle
+            turn(a, b, c) > 0.
+                && !remaining.iter().any(|&other| {
+                    let p = points[other as usize];
+                    p != a
+                        && p != b
+                        && p != c
+                        && turn(a, b, p) >= 0.
+                        && turn(b, c, p) >= 0.
+                        && turn(c, a, p) >= 0.
+                })
+        });
+        // degenerate (collinear or self intersecting) input has no ear, so clip any corner
+        // rather than loop forever
+        let i = ear.unwrap_or(0);
+        triangles.extend(corner(i));
+        remaining.remove(i);
+    }
+    triangles.extend(remaining);
+    triangles
+}
+
+fn add(a: Point2, b: Point2) -> Point2 {
+    Point2::new(a.x + b.x, a.y + b.y)
+}
+
+fn sub(a: Point2, b: Point2) -> Point2 {
+    Point2::new(a.x - b.x, a.y - b.y)
+}
+
+fn scale(a: Point2, factor: f32) -> Point2 {
+    Point2::new(a.x * factor, a.y * factor)
+}
+
+fn dot(a: Point2, b: Point2) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+fn cross(a: Point2, b: Point2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Unit vector from `from` towards `to`.
+fn direction(from: Point2, to: Point2) -> Point2 {
+    scale(sub(to, from), 1. / from.distance(to))
+}
+
+/// `dir` turned a quarter turn counter-clockwise.
+fn normal(dir: Point2) -> Point2 {
+    Point2::new(-dir.y, dir.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Area of a half circle of radius 1 built from round joins and caps.
+    fn half_turn_area() -> f32 {
+        let segments = ROUND_SEGMENTS_PER_HALF_TURN;
+        segments / 2. * (PI / segments).sin()
+    }
+
+    fn points(coordinates: &[(f32, f32)]) -> Vec<Point2> {
+        coordinates
+            .iter()
+            .map(|&(x, y)| Point2::new(x, y))
+            .collect()
+    }
+
+    fn reversed(points: &[Point2]) -> Vec<Point2> {
+        points.iter().rev().copied().collect()
+    }
+
+    /// Number of triangle edges marked as on the outline.
+    fn outline_edges(mesh: &Mesh) -> u32 {
+        mesh.outline.iter().map(|mask| mask.count_ones()).sum()
+    }
+
+    #[track_caller]
+    fn assert_mesh(mesh: &Mesh, triangles: usize, area: f32) {
+        assert_eq!(mesh.triangle_count(), triangles);
+        assert_eq!(mesh.outline.len(), triangles);
+        assert!(
+            (mesh.area() - area).abs() < 1e-3,
+            "area {} instead of {area}",
+            mesh.area()
+        );
+    }
+
+    #[test]
+    fn fills_convex_and_concave_polygons_in_either_winding() {
+        let square = points(&[(0., 0.), (10., 0.), (10., 10.), (0., 10.)]);
+        let c_shape = points(&[
+            (0., 0.),
+            (3., 0.),
+            (3., 1.),
+            (1., 1.),
+            (1., 2.),
+            (3., 2.),
+            (3., 3.),
+            (0., 3.),
+        ]);
+        for (polygon, triangles, area) in [(square, 2, 100.), (c_shape, 6, 7.)] {
+            assert_mesh(&fill(&polygon), triangles, area);
+            assert_mesh(&fill(&reversed(&polygon)), triangles, area);
+        }
+    }
+
+    #[test]
+    fn fills_mark_only_the_polygon_edges_as_outline() {
+        let arrow = points(&[(0., 0.), (6., 0.), (3., 2.), (6., 5.), (0., 5.)]);
+        for polygon in [arrow.clone(), reversed(&arrow)] {
+            let mesh = fill(&polygon);
+            assert_eq!(outline_edges(&mesh), 5);
+        }
+    }
+
+    #[test]
+    fn strokes_mark_the_sides_and_bare_ends_as_outline() {
+        let line = points(&[(0., 0.), (10., 0.)]);
+        let corner = points(&[(0., 0.), (10., 0.), (10., 10.)]);
+        for (path, cap, join, edges) in [
+            // two sides and two butt ends
+            (&line, LineCap::Butt, LineJoin::Miter, 4),
+            // the ends are covered by caps, each with three outer edges
+            (&line, LineCap::Square, LineJoin::Miter, 8),
+            (&line, LineCap::Round, LineJoin::Miter, 2 + 32),
+            // four sides, two ends and the two outer edges of the miter
+            (&corner, LineCap::Butt, LineJoin::Miter, 8),
+            (&corner, LineCap::Butt, LineJoin::Bevel, 7),
+        ] {
+            let style = StrokeStyle::new(2.).with_cap(cap).with_join(join);
+            let mesh = stroke(path, false, &style);
+            assert_eq!(outline_edges(&mesh), edges, "{cap:?} {join:?}");
+        }
+    }
+
+    #[test]
+    fn strokes_open_paths_with_each_cap() {
+        let line = points(&[(0., 0.), (10., 0.)]);
+        for (cap, triangles, area) in [
+            (LineCap::Butt, 2, 20.),
+            (LineCap::Square, 6, 24.),
+            (LineCap::Round, 34, 20. + 2. * half_turn_area()),
+        ] {
+            let style = StrokeStyle::new(2.).with_cap(cap);
+            assert_mesh(&stroke(&line, false, &style), triangles, area);
+        }
+    }
+
+    #[test]
+    fn strokes_open_paths_with_each_join() {
+        let corner = points(&[(0., 0.), (10., 0.), (10., 10.)]);
+        for (join, triangles, area) in [
+            (LineJoin::Miter, 6, 41.),
+            (LineJoin::Bevel, 5, 40.5),
+            (LineJoin::Round, 12, 40. + half_turn_area() / 2.),
+        ] {
+            let style = StrokeStyle::new(2.).with_join(join);
+            assert_mesh(&stroke(&corner, false, &style), triangles, area);
+            assert_mesh(&stroke(&reversed(&corner), false, &style), triangles, area);
+        }
+    }
+
+    #[test]
+    fn sharp_miters_past_the_limit_are_beveled() {
+        let sharp = points(&[(0., 0.), (10., 0.), (0., 1.)]);
+        let bevel_area = 20. + 2. * 101f32.sqrt() + 0.5 / 101f32.sqrt();
+        assert_mesh(&stroke(&sharp, false, &StrokeStyle::new(2.)), 5, bevel_area);
+        // the miter tip is about 20 widths out, so a limit of 30 keeps it
+        let style = StrokeStyle::new(2.).with_miter_limit(30.);
+        assert_eq!(stroke(&sharp, false, &style).triangle_count(), 6);
+    }
+
+    #[test]
+    fn u_turns_go_round_the_end() {
+        let u_turn = points(&[(0., 0.), (10., 0.), (0., 0.)]);
+        let round = StrokeStyle::new(2.).with_join(LineJoin::Round);
+        assert_mesh(&stroke(&u_turn, false, &round), 20, 40. + half_turn_area());
+        assert_mesh(&stroke(&u_turn, false, &StrokeStyle::new(2.)), 5, 40.);
+    }
+
+    #[test]
+    fn strokes_closed_squares_without_caps() {
+        let square = points(&[(0., 0.), (10., 0.), (10., 10.), (0., 10.)]);
+        for (join, triangles, area) in [
+            (LineJoin::Miter, 16, 84.),
+            (LineJoin::Bevel, 12, 82.),
+            (LineJoin::Round, 40, 80. + 2. * half_turn_area()),
+        ] {
+            let style = StrokeStyle::new(2.)
+                .with_join(join)
+                .with_cap(LineCap::Round);
+            assert_mesh(&stroke(&square, true, &style), triangles, area);
+            assert_mesh(&stroke(&reversed(&square), true, &style), triangles, area);
+        }
+    }
+}
@@ -1,51 +1,59 @@
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use rand::Rng;
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
-
-use wgpu::{
-    Adapter, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
-    ComputePipelineDescriptor, Device, Queue, RequestDeviceError, ShaderStages, Surface,
-    VertexAttribute, VertexBufferLayout, VertexFormat,
-};
+use wgpu::{Adapter, Device, Queue, RequestDeviceError, Surface};
 
 use winit::{
     application::ApplicationHandler,
+    dpi::PhysicalPosition,
     event::*,
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
-    // keyboard::{KeyCode, PhysicalKey},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
     window::{Window, WindowAttributes},
 };
 
-use crate::primitives::GRID_SIZE;
-use crate::render_context::RenderContext;
-
-const FPS_CAP: f32 = 20.;
+use crate::cli::Options;
+use crate::clock::SimulationClock;
+use crate::pattern::Pattern;
+use crate::render_context::{GridSizeError, RenderContext};
 
 impl<'a> App<'a> {
-    pub fn init(window_attributes: Option<WindowAttributes>) -> App<'a> {
-        let window_attributes = match window_attributes {
-            Some(w) => w,
-            None => WindowAttributes::default(),
-        };
+    /// Opens a window and runs the simulation set up by `options`, starting from `pattern` if
+    /// one was loaded, until the window is closed or `options.generations` have run.
+    pub fn init(
+        window_attributes: Option<WindowAttributes>,
+        options: Options,
+        pattern: Option<Pattern>,
+    ) -> App<'a> {
+        let (width, height) = options.window_size;
+        let window_attributes = window_attributes
+            .unwrap_or_default()
+            .with_inner_size(winit::dpi::PhysicalSize::new(width, height));
         let mut app = App {
             window_attributes,
             window: None,
             render_ctx: None,
-            frame_time: Instant::now(),
+            clock: SimulationClock::new(options.tick_rate),
+            paused: false,
+            pending_steps: 0,
+            cursor: None,
+            brush: None,
+            panning: false,
+            modifiers: ModifiersState::empty(),
+            options,
+            pattern,
         };
 
         app.init_eventloop_and_window();
 
         app
     }
-    fn init_eventloop_and_window(&mut self) -> () {
+    fn init_eventloop_and_window(&mut self) {
         let event_loop = EventLoop::new().unwrap();
         event_loop.set_control_flow(ControlFlow::Wait);
         event_loop.run_app(self).unwrap();
     }
-    pub fn init_renderer(&mut self) {
+    pub fn init_renderer(&mut self) -> Result<(), GridSizeError> {
         eprintln!("init_renderer");
         let window = self
             .window
@@ -72,258 +80,99 @@ impl<'a> App<'a> {
             pollster::block_on(request_device(&adapter)).expect("ERROR: setting up device failed.");
 
         //
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps.formats[0]; //first format is the preferred format
         let config = surface
             .get_default_config(&adapter, size.width, size.height)
             .unwrap();
         surface.configure(&device, &config);
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-        const WORKGROUP_SIZE: u8 = 8;
-        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Game of life simulation"),
-            source: wgpu::ShaderSource::Wgsl(
-                r#"
-                    @group(0) @binding(0) var<uniform> grid: vec2f;
-                    @group(0) @binding(1) var<storage> cell_state_in: array<u32>;
-                    @group(0) @binding(2) var<storage, read_write> cell_state_out: array<u32>;
-
-                    fn cellIndex(cell: vec2u) -> u32 {
-                        return (cell.y % u32(grid.y)) * u32(grid.x) + (cell.x % u32(grid.x));
-                    }
-                    fn cellActive(x: u32, y: u32) -> u32 {
-                        return cell_state_in[cellIndex(vec2(x, y))];
-                    }
-                    @compute @workgroup_size(8,8)
-                    fn compute_main(@builtin(global_invocation_id) cell: vec3u) {
-                    // getting count of active neighbors 
-                        let active_neighbors = cellActive(cell.x+1, cell.y+1) +
-                            cellActive(cell.x+1, cell.y) +
-                            cellActive(cell.x+1, cell.y-1) +
-                            cellActive(cell.x, cell.y-1) +
-                            cellActive(cell.x-1, cell.y-1) +
-                            cellActive(cell.x-1, cell.y) +
-                            cellActive(cell.x-1, cell.y+1) +
-                            cellActive(cell.x, cell.y+1);
-
-                        let i = cellIndex(cell.xy);
-                        switch active_neighbors{
-                            case 2u:{
-                                cell_state_out[i] = cell_state_in[i];
-                            }
-                            case 3u:{
-                                cell_state_out[i] = 1u;
-                            }
-                            default:{
-                                cell_state_out[i] = 0u;
-                            }
-                        }
-                    }
-            "#
-                .into(),
-            ),
-        });
-
-        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("Bind Group Layout"),
-            entries: &[
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
-
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout], // even though there are two bind groups, only one is used at a time so only one layout is necessary
-            push_constant_ranges: &[],
-        });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[VertexBufferLayout {
-                    array_stride: 8,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[VertexAttribute {
-                        format: VertexFormat::Float32x2,
-                        offset: 0,
-                        shader_location: 0,
-                    }],
-                }],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format.into(),
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
-                // or Features::POLYGON_MODE_POINT
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            // If the pipeline will be used with a multiview render pass, this
-            // indicates how many array layers the attachments will have.
-            multiview: None,
-        });
-
-        let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-            label: Some("Compute Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &compute_shader,
-            entry_point: "compute_main",
-            compilation_options: Default::default(),
-        });
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(crate::primitives::VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(crate::primitives::INDICES),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(crate::primitives::UNIFORM_ARRAY),
-            usage: wgpu::BufferUsages::UNIFORM,
-        });
-
-        let cell_state_array = &mut [0u32; (GRID_SIZE * GRID_SIZE) as usize];
-        let mut rng = rand::thread_rng();
-        for val in cell_state_array.iter_mut() {
-            let rng_val: u8 = rng.gen_range(0..100);
-            *val = if rng_val > 60 { 1 } else { 0 };
-        }
-
-        let cell_storage_buffers = [
-            device.create_buffer_init(&BufferInitDescriptor {
-                label: Some("Cell storage buffer A"),
-                contents: bytemuck::cast_slice(cell_state_array),
-                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            }),
-            device.create_buffer_init(&BufferInitDescriptor {
-                label: Some("Cell storage buffer B"),
-                contents: bytemuck::cast_slice(cell_state_array),
-                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            }),
-        ];
-
-        let bind_groups = [
-            device.create_bind_group(&BindGroupDescriptor {
-                label: Some("Uniform and Storage Bind Group A"),
-                layout: &render_pipeline.get_bind_group_layout(0),
-                entries: &[
-                    BindGroupEntry {
-                        binding: 0,
-                        resource: uniform_buffer.as_entire_binding(),
-                    },
-                    BindGroupEntry {
-                        binding: 1,
-                        resource: cell_storage_buffers[0].as_entire_binding(),
-                    },
-                    BindGroupEntry {
-                        binding: 2,
-                        resource: cell_storage_buffers[1].as_entire_binding(),
-                    },
-                ],
-            }),
-            device.create_bind_group(&BindGroupDescriptor {
-                label: Some("Uniform and Storage Bind Group B"),
-                layout: &render_pipeline.get_bind_group_layout(0),
-                entries: &[
-                    BindGroupEntry {
-                        binding: 0,
-                        resource: uniform_buffer.as_entire_binding(),
-                    },
-                    BindGroupEntry {
-                        binding: 1,
-                        resource: cell_storage_buffers[1].as_entire_binding(),
-                    },
-                    BindGroupEntry {
-                        binding: 2,
-                        resource: cell_storage_buffers[0].as_entire_binding(),
-                    },
-                ],
-            }),
-        ];
-
-        let render_ctx = RenderContext::new(
+        let mut render_ctx = RenderContext::new(
             surface,
             device,
             queue,
             config,
             size,
-            render_pipeline,
-            compute_pipeline,
-            vertex_buffer,
-            index_buffer,
-            uniform_buffer,
-            cell_storage_buffers,
-            bind_groups,
-        );
+            self.options.grid_size,
+            self.options.antialiasing,
+        )?;
+        self.options
+            .configure(&mut render_ctx, self.pattern.as_ref());
         self.render_ctx = Some(render_ctx);
+        self.clock.reset();
+        Ok(())
+    }
+
+    /// Applies the key bindings:
+    ///
+    /// - space pauses and resumes
+    /// - N or right arrow advances one generation while paused
+    /// - R reseeds with a new random seed, C clears the board
+    /// - + and - double or halve the simulation rate, or the step rate with `--speed`
+    /// - F zooms to fit the whole board
+    fn handle_key(&mut self, code: KeyCode, repeat: bool) {
+        let Some(ctx) = self.render_ctx.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Space if !repeat => {
+                self.paused = !self.paused;
+                // don't catch up on the time spent paused
+                self.clock.reset();
+                eprintln!("{}", if self.paused { "paused" } else { "resumed" });
+            }
+            KeyCode::KeyN | KeyCode::ArrowRight if self.paused => self.pending_steps += 1,
+            KeyCode::KeyR if !repeat => {
+                let seeding = ctx.seeding().with_seed(rand::random());
+                ctx.reseed(seeding);
+                eprintln!("seed: {}", seeding.seed);
+            }
+            KeyCode::KeyC if !repeat => ctx.clear(),
+            KeyCode::KeyF if !repeat => ctx.fit_camera(),
+            KeyCode::Equal | KeyCode::NumpadAdd if self.options.step_rate.is_some() => {
+                ctx.set_step_rate(ctx.step_rate().doubled());
+                eprintln!("speed: {} generations/frame", ctx.step_rate());
+            }
+            KeyCode::Minus | KeyCode::NumpadSubtract if self.options.step_rate.is_some() => {
+                ctx.set_step_rate(ctx.step_rate().halved());
+                eprintln!("speed: {} generations/frame", ctx.step_rate());
+            }
+            KeyCode::Equal | KeyCode::NumpadAdd => {
+                self.clock.set_rate(self.clock.rate() * 2.);
+                eprintln!("rate: {} generations/s", self.clock.rate());
+            }
+            KeyCode::Minus | KeyCode::NumpadSubtract => {
+                self.clock.set_rate(self.clock.rate() / 2.);
+                eprintln!("rate: {} generations/s", self.clock.rate());
+            }
+            _ => return,
+        }
+        // paused windows only redraw on demand
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Paints from the last painted cell to the one under the cursor, if a button is held.
+    fn paint(&mut self) {
+        let (Some(ctx), Some((state, last)), Some(cursor)) =
+            (self.render_ctx.as_mut(), self.brush.as_mut(), self.cursor)
+        else {
+            return;
+        };
+        let Some(cell) = ctx.cell_at(cursor.x, cursor.y) else {
+            // start a new stroke when coming back onto the board
+            *last = None;
+            return;
+        };
+        ctx.paint_line(last.unwrap_or(cell), cell, *state);
+        *last = Some(cell);
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Writes the `--output` and `--screenshot` files, if any were asked for.
+    fn write_outputs(&self) {
+        if let Some(ctx) = self.render_ctx.as_ref() {
+            if let Err(e) = self.options.write_outputs(ctx) {
+                eprintln!("ERROR: writing output failed: {e}");
+            }
+        }
     }
 }
 
@@ -331,7 +180,19 @@ pub struct App<'a> {
     window: Option<Arc<Window>>,
     window_attributes: WindowAttributes,
     render_ctx: Option<RenderContext<'a>>,
-    frame_time: Instant,
+    clock: SimulationClock,
+    paused: bool,
+    /// Single steps requested while paused, computed on the next redraw.
+    pending_steps: u64,
+    /// Last cursor position over the window, in pixels.
+    cursor: Option<PhysicalPosition<f64>>,
+    /// State being painted while a mouse button is held, and the cell painted last.
+    brush: Option<(u32, Option<(u32, u32)>)>,
+    /// Whether the view is being dragged around.
+    panning: bool,
+    modifiers: ModifiersState,
+    options: Options,
+    pattern: Option<Pattern>,
 }
 
 impl<'a> ApplicationHandler for App<'a> {
@@ -341,7 +202,20 @@ impl<'a> ApplicationHandler for App<'a> {
         self.window = Some(Arc::new(
             event_loop.create_window(window_attributes).unwrap(),
         ));
-        self.init_renderer();
+        if let Err(e) = self.init_renderer() {
+            eprintln!("ERROR: {e}");
+            event_loop.exit();
+        }
+    }
+
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
+        // the wait between frames capped by --fps is over
+        if let StartCause::ResumeTimeReached { .. } = cause {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            if let Some(window) = self.window.as_ref() {
+                window.request_redraw();
+            }
+        }
     }
 
     fn window_event(
@@ -353,6 +227,7 @@ impl<'a> ApplicationHandler for App<'a> {
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
+                self.write_outputs();
                 event_loop.exit();
             }
             WindowEvent::RedrawRequested => {
@@ -378,20 +253,110 @@ impl<'a> ApplicationHandler for App<'a> {
                 // You only need to call this if you've determined that you need to redraw in
                 // applications which do not always need to. Applications that redraw continuously
                 // can render here instead.
+                let frame_start = Instant::now();
                 if let Some(ctx) = self.render_ctx.as_mut() {
-                    let frame_time = self.frame_time.elapsed();
-                    if frame_time.as_secs_f32() >= 1. / FPS_CAP {
-                        // println!("Time: {:?}", self.frame_time.elapsed());
-                        ctx.render();
-                        self.frame_time = Instant::now();
+                    // drawing follows the display's refresh rate (or --fps), and the clock
+                    // decides how many generations fit in between, unless --speed ties them to
+                    // frames instead
+                    let mut due = if self.paused {
+                        std::mem::take(&mut self.pending_steps)
+                    } else if self.options.step_rate.is_some() {
+                        ctx.advance_frame()
+                    } else {
+                        self.clock.tick()
+                    };
+                    if let Some(generations) = self.options.generations {
+                        due = due.min(generations.saturating_sub(ctx.generation()));
+                    }
+                    ctx.render_generations(due);
+                    if self
+                        .options
+                        .generations
+                        .is_some_and(|generations| ctx.generation() >= generations)
+                    {
+                        self.write_outputs();
+                        event_loop.exit();
+                        return;
+                    }
+                }
+
+                if !self.paused {
+                    match self.options.fps_cap {
+                        // new_events asks for the redraw once the wait is over
+                        Some(fps) => event_loop.set_control_flow(ControlFlow::WaitUntil(
+                            frame_start + Duration::from_secs_f64(1. / fps),
+                        )),
+                        None => self.window.as_ref().unwrap().request_redraw(),
                     }
                 }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code),
+                        state: ElementState::Pressed,
+                        repeat,
+                        ..
+                    },
+                ..
+            } => self.handle_key(code, repeat),
 
-                self.window.as_ref().unwrap().request_redraw();
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::CursorMoved { position, .. } => {
+                if let (true, Some(last), Some(ctx)) =
+                    (self.panning, self.cursor, self.render_ctx.as_mut())
+                {
+                    let mut camera = ctx.camera();
+                    camera.pan(position.x - last.x, position.y - last.y);
+                    ctx.set_camera(camera);
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+                self.cursor = Some(position);
+                self.paint();
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            // the middle button, or ctrl and the left button, drags the view, otherwise the
+            // left button draws live cells and the right button erases them
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pans = button == MouseButton::Middle
+                    || (button == MouseButton::Left && self.modifiers.control_key());
+                let paint_state = match button {
+                    _ if pans => None,
+                    MouseButton::Left => Some(1),
+                    MouseButton::Right => Some(0),
+                    _ => return,
+                };
+                match state {
+                    ElementState::Pressed => {
+                        self.panning = pans;
+                        self.brush = paint_state.map(|state| (state, None));
+                        self.paint();
+                    }
+                    ElementState::Released => {
+                        self.panning = false;
+                        self.brush = None;
+                    }
+                }
+            }
+            // zoom towards the cursor, a line on the wheel being about 10%
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y as f64,
+                    MouseScrollDelta::PixelDelta(position) => position.y / 40.,
+                };
+                if let (Some(ctx), Some(cursor)) = (self.render_ctx.as_mut(), self.cursor) {
+                    let mut camera = ctx.camera();
+                    camera.zoom_at(1.1f64.powf(lines), cursor.x, cursor.y, ctx.size());
+                    ctx.set_camera(camera);
+                    self.window.as_ref().unwrap().request_redraw();
+                }
             }
 
             WindowEvent::Resized(new_size) => {
-                self.render_ctx.as_mut().unwrap().resize(new_size);
+                if let Some(ctx) = self.render_ctx.as_mut() {
+                    ctx.resize(new_size);
+                }
             }
             _ => (),
         }
@@ -417,17 +382,17 @@ pub async fn request_adapter<'a>(
         .request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::default(),
             force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
+            compatible_surface: Some(surface),
         })
         .await
 }
 
-pub async fn request_device<'a>(adapter: &Adapter) -> Result<(Device, Queue), RequestDeviceError> {
+pub async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), RequestDeviceError> {
     adapter
         .request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::VERTEX_WRITABLE_STORAGE,
+                required_features: wgpu::Features::empty(),
                 required_limits: wgpu::Limits::default(),
             },
             None,